@@ -0,0 +1,372 @@
+//! Solving many puzzles in one run, such as a whole puzzle collection, with a
+//! line per puzzle and a summary table at the end.
+//!
+//! Puzzles are shared out between a pool of worker threads. z3 keeps one
+//! context per thread, so every worker builds its own solvers and nothing z3
+//! owns crosses threads. Results are printed in input order regardless of
+//! which worker finishes first.
+//!
+//! Results can also be written as JSON, one object per line.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::json;
+
+use crate::{
+    Limits, Puzzle, Solver, Status, UnknownReason,
+    grid::{Cell, Conflict, Digit},
+    logic::Explanation,
+    parse::Parsed,
+};
+
+/// Result of solving one puzzle of a batch.
+#[derive(Debug)]
+pub struct Outcome {
+    pub status: Status,
+    /// The first solution found, if any.
+    pub solution: Option<Puzzle>,
+    /// Number of solutions found, at most `limit`.
+    pub solution_count: usize,
+    /// Most solutions the solver was asked for.
+    pub limit: usize,
+    /// Why the solver gave up, if it did.
+    pub unknown: Option<UnknownReason>,
+    /// Givens that rule out any solution, found before solving.
+    pub conflicts: Vec<Conflict>,
+    /// A minimal set of contradictory givens, for unsatisfiable puzzles
+    /// without conflicts.
+    pub contradiction: Option<Vec<(Cell, Digit)>>,
+    /// Name of the backend that solved the puzzle.
+    pub backend: &'static str,
+    /// Steps taken by backends that solve the way a person would.
+    pub explanation: Option<Explanation>,
+    pub elapsed: Duration,
+}
+
+impl Outcome {
+    /// Describes solving `input` as JSON, with grids as arrays of rows in
+    /// which 0 marks an empty cell, and 1-based rows and columns.
+    pub fn to_json(&self, input: &Puzzle) -> serde_json::Value {
+        let mut result = json!({
+            "input": input.to_array(),
+            "givens": givens_json(&input.givens()),
+            "status": self.status.to_string(),
+            "solution": self.solution.as_ref().map(Puzzle::to_array),
+            "solution_count": self.solution_count,
+            "limit": self.limit,
+            "backend": self.backend,
+            "time_ms": self.elapsed.as_secs_f64() * 1000.0,
+        });
+
+        if !self.conflicts.is_empty() {
+            result["conflicts"] = self
+                .conflicts
+                .iter()
+                .map(|conflict| {
+                    let [first, second] = conflict.cells;
+                    json!({
+                        "cells": [
+                            { "row": first.row() + 1, "col": first.col() + 1 },
+                            { "row": second.row() + 1, "col": second.col() + 1 },
+                        ],
+                        "digit": conflict.digit.get(),
+                    })
+                })
+                .collect();
+        }
+        if let Some(contradiction) = &self.contradiction {
+            result["contradiction"] = givens_json(contradiction);
+        }
+        if let Some(reason) = &self.unknown {
+            result["reason"] = reason.to_string().into();
+        }
+        if let Some(explanation) = &self.explanation {
+            result["explanation"] = explanation.to_json();
+        }
+
+        result
+    }
+}
+
+/// Lists `givens` as JSON objects with 1-based rows and columns.
+fn givens_json(givens: &[(Cell, Digit)]) -> serde_json::Value {
+    givens
+        .iter()
+        .map(|(cell, digit)| {
+            json!({ "row": cell.row() + 1, "col": cell.col() + 1, "digit": digit.get() })
+        })
+        .collect()
+}
+
+/// Looks for up to `limit` solutions of `puzzle`. A limit of 2 or more tells
+/// whether the solution is unique. Puzzles whose givens conflict aren't
+/// handed to the solver at all.
+pub fn solve(puzzle: &Puzzle, solver: &dyn Solver, limit: usize, limits: &Limits) -> Outcome {
+    let start = Instant::now();
+
+    let conflicts = puzzle.conflicts();
+    if !conflicts.is_empty() {
+        return Outcome {
+            status: Status::Unsat,
+            solution: None,
+            solution_count: 0,
+            limit,
+            unknown: None,
+            conflicts,
+            contradiction: None,
+            backend: solver.name(),
+            explanation: None,
+            elapsed: start.elapsed(),
+        };
+    }
+
+    let found = solver.find_solutions(puzzle, limit, limits);
+
+    let solution_count = found.solutions.len();
+    // A solver that gave up hasn't shown there are no more solutions, so a
+    // single one isn't known to be unique. Like `Puzzle::solve`, call that
+    // unknown rather than solved
+    let status = match solution_count {
+        _ if found.unknown.is_some() => Status::Unknown,
+        0 => Status::Unsat,
+        1 => Status::Solved,
+        _ => Status::Multiple,
+    };
+
+    let contradiction = match status {
+        Status::Unsat => puzzle.contradiction_since(limits, start),
+        _ => None,
+    };
+
+    Outcome {
+        status,
+        solution: found.solutions.into_iter().next(),
+        solution_count,
+        limit,
+        unknown: found.unknown,
+        conflicts: Vec::new(),
+        contradiction,
+        backend: solver.name(),
+        explanation: found.explanation,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Solves every puzzle with `solver` on `workers` threads, each within
+/// `limits`, writing a line for each in input order as soon as it and all
+/// before it are done, then the summary table. With `as_json`, each line is
+/// instead a JSON object as described by [`Outcome::to_json`] and there's no
+/// summary.
+pub fn run(
+    puzzles: &[Parsed],
+    solver: &dyn Solver,
+    workers: usize,
+    limits: &Limits,
+    as_json: bool,
+    out: &mut dyn Write,
+) -> io::Result<Summary> {
+    if !as_json {
+        writeln!(
+            out,
+            "{:>6}  {:<8}  {:>10}  puzzle",
+            "line", "status", "time"
+        )?;
+    }
+
+    let start = Instant::now();
+    let mut summary = Summary::default();
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| -> io::Result<()> {
+        for _ in 0..workers.max(1) {
+            let sender = sender.clone();
+            let next = &next;
+
+            scope.spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(parsed) = puzzles.get(index) else {
+                        break;
+                    };
+
+                    let puzzle = Puzzle::from_array(&parsed.grid);
+                    let outcome = solve(&puzzle, solver, 2, limits);
+
+                    if sender.send((index, puzzle, outcome)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // Hold on to results that finish early until the ones before them
+        // have been printed
+        let mut pending = BTreeMap::new();
+        let mut printed = 0;
+
+        for (index, puzzle, outcome) in receiver {
+            pending.insert(index, (puzzle, outcome));
+
+            while let Some((puzzle, outcome)) = pending.remove(&printed) {
+                if as_json {
+                    let mut result = outcome.to_json(&puzzle);
+                    result["line"] = puzzles[printed].line.into();
+                    if let Some(comment) = &puzzles[printed].comment {
+                        result["comment"] = comment.as_str().into();
+                    }
+                    writeln!(out, "{result}")?;
+                } else {
+                    write_outcome(out, &puzzles[printed], &puzzle, &outcome)?;
+                }
+                summary.add(&outcome);
+                printed += 1;
+            }
+        }
+
+        Ok(())
+    })?;
+
+    if !as_json {
+        writeln!(out, "\n{summary}")?;
+        writeln!(
+            out,
+            "Solved {} puzzles on {} workers in {:.3} s",
+            puzzles.len(),
+            workers.max(1),
+            start.elapsed().as_secs_f64()
+        )?;
+    }
+
+    Ok(summary)
+}
+
+fn write_outcome(
+    out: &mut dyn Write,
+    parsed: &Parsed,
+    puzzle: &Puzzle,
+    outcome: &Outcome,
+) -> io::Result<()> {
+    let shown = outcome.solution.as_ref().unwrap_or(puzzle).to_line();
+    write!(
+        out,
+        "{:>6}  {:<8}  {:>10}  {shown}",
+        parsed.line,
+        outcome.status,
+        format_duration(outcome.elapsed)
+    )?;
+    match &parsed.comment {
+        Some(comment) => writeln!(out, "  {comment}"),
+        None => writeln!(out),
+    }
+}
+
+/// Per-status counts and times of a batch.
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// Count and total time of each status, indexed like `Status::ALL`.
+    totals: [(usize, Duration); 4],
+}
+
+impl Summary {
+    pub fn add(&mut self, outcome: &Outcome) {
+        let total = &mut self.totals[Self::index(outcome.status)];
+        total.0 += 1;
+        total.1 += outcome.elapsed;
+    }
+
+    /// Number of puzzles that ended with `status`.
+    pub fn count(&self, status: Status) -> usize {
+        self.totals[Self::index(status)].0
+    }
+
+    fn index(status: Status) -> usize {
+        Status::ALL
+            .iter()
+            .position(|&s| s == status)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let row = |f: &mut fmt::Formatter<'_>, label: &dyn fmt::Display, count, total| {
+            let mean = if count == 0 {
+                Duration::ZERO
+            } else {
+                total / count as u32
+            };
+
+            writeln!(
+                f,
+                "{label:<8}  {count:>7}  {:>10}  {:>10}",
+                format_duration(total),
+                format_duration(mean)
+            )
+        };
+
+        writeln!(
+            f,
+            "{:<8}  {:>7}  {:>10}  {:>10}",
+            "status", "count", "total", "mean"
+        )?;
+
+        for (status, &(count, total)) in Status::ALL.iter().zip(&self.totals) {
+            row(f, status, count, total)?;
+        }
+
+        let count = self.totals.iter().map(|&(count, _)| count).sum();
+        let total = self.totals.iter().map(|&(_, total)| total).sum();
+        row(f, &"all", count, total)
+    }
+}
+
+/// Formats `duration` in milliseconds, which suits single solves.
+fn format_duration(duration: Duration) -> String {
+    format!("{:.3} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Found;
+
+    /// Finds the puzzle itself as a solution, then gives up on looking for
+    /// another.
+    struct GivesUpAfterOne;
+
+    impl Solver for GivesUpAfterOne {
+        fn name(&self) -> &'static str {
+            "gives-up"
+        }
+
+        fn find_solutions(&self, puzzle: &Puzzle, _limit: usize, _limits: &Limits) -> Found {
+            Found {
+                solutions: vec![puzzle.clone()],
+                unknown: Some(UnknownReason::Timeout),
+                explanation: None,
+            }
+        }
+    }
+
+    #[test]
+    fn one_solution_then_giving_up_is_unknown() {
+        let puzzle = Puzzle::new(crate::grid::Grid::new());
+        let outcome = solve(&puzzle, &GivesUpAfterOne, 2, &Limits::new());
+
+        assert_eq!(outcome.status, Status::Unknown);
+        assert_eq!(outcome.unknown, Some(UnknownReason::Timeout));
+    }
+}
@@ -0,0 +1,1060 @@
+//! Command-line interface.
+//!
+//! Every subcommand reads puzzles with [`parse::read_puzzles`], so an input
+//! may hold one puzzle or many, in either text format. Exit codes let scripts
+//! tell outcomes apart without reading the output; see [`Exit`].
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    thread,
+    time::Duration,
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use crossterm::style::Stylize;
+use serde_json::json;
+
+use sudoku_solver::{
+    BACKENDS, Encoding, Limits, Puzzle, SolveMode, SolveOutcome, Solver, Status, UnknownReason,
+    Z3Solver,
+    batch::{self, Summary},
+    dimacs::{self, ModelError},
+    generator::{self, Generator, Symmetry},
+    native,
+    parse::{self, Format, InputError, Parsed},
+    rating::{self, Difficulty, Rating, RatingError},
+};
+
+use crate::SudokuInput;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Solved, or the command succeeded
+  1  A puzzle has no solution
+  2  Invalid input
+  3  A puzzle has more than one solution
+  4  The solver or generator gave up
+  5  Could not read or write a file";
+
+/// Process exit codes. When a batch has several outcomes the lowest listed
+/// one other than `Success` wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    Success = 0,
+    Unsat = 1,
+    InvalidInput = 2,
+    Multiple = 3,
+    Unknown = 4,
+    Io = 5,
+}
+
+impl Exit {
+    /// The exit code of a batch in which both `self` and `other` happened.
+    fn and(self, other: Exit) -> Exit {
+        match (self, other) {
+            (Exit::Success, exit) | (exit, Exit::Success) => exit,
+            (first, second) => {
+                if first as u8 <= second as u8 {
+                    first
+                } else {
+                    second
+                }
+            }
+        }
+    }
+}
+
+impl From<Status> for Exit {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Solved => Exit::Success,
+            Status::Unsat => Exit::Unsat,
+            Status::Unknown => Exit::Unknown,
+            Status::Multiple => Exit::Multiple,
+        }
+    }
+}
+
+impl From<&Summary> for Exit {
+    fn from(summary: &Summary) -> Self {
+        [Status::Unsat, Status::Multiple, Status::Unknown]
+            .into_iter()
+            .find(|&status| summary.count(status) > 0)
+            .map_or(Exit::Success, Exit::from)
+    }
+}
+
+/// How puzzles are written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// A boxed table, with solved cells highlighted
+    #[default]
+    Pretty,
+    /// 9 lines of 9 characters, which can be read back in
+    Grid,
+    /// All 81 cells on one line, which can be read back in
+    Line,
+    /// JSON objects; solve results include status, timing and givens
+    Json,
+}
+
+impl OutputFormat {
+    fn write_puzzle(self, out: &mut Output, puzzle: &Puzzle) -> io::Result<()> {
+        match self {
+            OutputFormat::Pretty => writeln!(out, "{}", out.table(puzzle)),
+            OutputFormat::Grid => writeln!(out, "{}", puzzle.to_grid()),
+            OutputFormat::Line => writeln!(out, "{}", puzzle.to_line()),
+            OutputFormat::Json => writeln!(out, "{}", json!({ "puzzle": puzzle.to_array() })),
+        }
+    }
+
+    /// Writes `puzzle` with a note about it, such as its rating, placed so
+    /// that grids and lines can still be read back in.
+    fn write_noted(self, out: &mut Output, puzzle: &Puzzle, note: &str) -> io::Result<()> {
+        match self {
+            OutputFormat::Pretty => writeln!(out, "{}{note}\n", out.table(puzzle)),
+            OutputFormat::Grid => writeln!(out, "# {note}\n{}", puzzle.to_grid()),
+            OutputFormat::Line => writeln!(out, "{}  {note}", puzzle.to_line()),
+            OutputFormat::Json => writeln!(
+                out,
+                "{}",
+                json!({ "puzzle": puzzle.to_array(), "note": note })
+            ),
+        }
+    }
+
+    /// Writes `puzzle` with its rating, or why it has none. As JSON, the
+    /// rating's fields are written alongside the puzzle.
+    fn write_rated(
+        self,
+        out: &mut Output,
+        puzzle: &Puzzle,
+        rating: &Result<Rating, RatingError>,
+    ) -> io::Result<()> {
+        match (self, rating) {
+            (OutputFormat::Json, Ok(rating)) => {
+                let mut result = rating.to_json();
+                result["puzzle"] = json!(puzzle.to_array());
+                writeln!(out, "{result}")
+            }
+            (OutputFormat::Json, Err(e)) => writeln!(
+                out,
+                "{}",
+                json!({ "puzzle": puzzle.to_array(), "error": e.to_string() })
+            ),
+            (_, Ok(rating)) => self.write_noted(out, puzzle, &rating.to_string()),
+            (_, Err(e)) => self.write_noted(out, puzzle, &e.to_string()),
+        }
+    }
+
+    /// Format to use for `parsed` when none was asked for: the same layout it
+    /// was read in, but boxed if it was a grid.
+    fn matching(parsed: &Parsed) -> Self {
+        match parsed.format {
+            Format::Grid => OutputFormat::Pretty,
+            Format::Line => OutputFormat::Line,
+        }
+    }
+}
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Solve, generate and rate sudoku puzzles",
+    long_about = "Solve, generate and rate sudoku puzzles.\n\n\
+        Without a subcommand, opens the editor when run in a terminal and \
+        otherwise solves the puzzles on stdin.",
+    after_help = EXIT_CODES,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    solve: SolveArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Solve puzzles, checking a batch of them for unique solutions
+    Solve(SolveArgs),
+    /// Generate puzzles with a unique solution
+    Generate(GenerateArgs),
+    /// Rate how hard puzzles are to solve by hand
+    Rate(IoArgs),
+    /// Check that puzzles have exactly one solution
+    Validate(ValidateArgs),
+    /// Rewrite puzzles in another format
+    Convert(IoArgs),
+    /// Enter a puzzle in the interactive editor, then solve it
+    Edit(IoArgs),
+    /// Play a puzzle in the terminal
+    Play(PlayArgs),
+    /// Compare how fast backends solve puzzles, by default z3 with each
+    /// encoding
+    Bench(BenchArgs),
+    /// Write the rules and givens of a puzzle as DIMACS CNF for a SAT solver;
+    /// as JSON, an object holding it
+    Cnf(IoArgs),
+    /// Read a SAT solver's model of a puzzle's CNF back into the puzzle
+    Model(ModelArgs),
+    /// Write the z3 assertions for a puzzle as an SMT-LIB2 script; as JSON,
+    /// an object holding it
+    Smt2(Smt2Args),
+}
+
+#[derive(Args)]
+struct OutputArgs {
+    /// File to write to, stdout if omitted
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// How to write puzzles
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+}
+
+#[derive(Args)]
+struct IoArgs {
+    /// File to read puzzles from, stdin if omitted
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+/// What to find out about the solutions of a single puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ModeArg {
+    /// Find one solution
+    First,
+    /// Find one solution and check that it's the only one
+    Unique,
+    /// Count the solutions
+    Count,
+}
+
+/// How the z3 backend models the digit of a cell, see [`Encoding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum EncodingArg {
+    /// An integer per cell
+    Int,
+    /// A boolean per cell and digit
+    OneHot,
+    /// A 4-bit bit-vector per cell
+    BitVec,
+}
+
+impl From<EncodingArg> for Encoding {
+    fn from(arg: EncodingArg) -> Self {
+        match arg {
+            EncodingArg::Int => Encoding::Int,
+            EncodingArg::OneHot => Encoding::OneHot,
+            EncodingArg::BitVec => Encoding::BitVec,
+        }
+    }
+}
+
+/// Symmetry of the givens' layout, see [`Symmetry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SymmetryArg {
+    None,
+    /// Unchanged by a half turn about the centre
+    Rotational,
+    /// Mirrored across the main diagonal
+    Diagonal,
+    /// Mirrored across the middle row
+    Horizontal,
+    /// Mirrored across the middle column
+    Vertical,
+    /// Unchanged by every rotation and reflection of the square
+    Dihedral,
+}
+
+impl From<SymmetryArg> for Symmetry {
+    fn from(arg: SymmetryArg) -> Self {
+        match arg {
+            SymmetryArg::None => Symmetry::None,
+            SymmetryArg::Rotational => Symmetry::Rotational,
+            SymmetryArg::Diagonal => Symmetry::Diagonal,
+            SymmetryArg::Horizontal => Symmetry::Horizontal,
+            SymmetryArg::Vertical => Symmetry::Vertical,
+            SymmetryArg::Dihedral => Symmetry::Dihedral,
+        }
+    }
+}
+
+/// Coarse difficulty label, see [`Difficulty`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DifficultyArg {
+    /// Singles only
+    Easy,
+    /// Needs pointing pairs or box/line reductions
+    Medium,
+    /// Needs subsets of up to three cells, X-Wings or Swordfish
+    Hard,
+    /// Needs wings, quads or colouring
+    Expert,
+    /// Can't be finished by the logical solver
+    Diabolical,
+}
+
+impl From<DifficultyArg> for Difficulty {
+    fn from(arg: DifficultyArg) -> Self {
+        match arg {
+            DifficultyArg::Easy => Difficulty::Easy,
+            DifficultyArg::Medium => Difficulty::Medium,
+            DifficultyArg::Hard => Difficulty::Hard,
+            DifficultyArg::Expert => Difficulty::Expert,
+            DifficultyArg::Diabolical => Difficulty::Diabolical,
+        }
+    }
+}
+
+#[derive(Args)]
+struct SolveArgs {
+    #[command(flatten)]
+    io: IoArgs,
+
+    /// Solver backend: z3, z3-onehot, z3-bitvec, native, dlx or logic
+    #[arg(short, long, default_value = "z3", value_parser = backend)]
+    backend: &'static dyn Solver,
+
+    /// What to find out about a single puzzle; batches always check for
+    /// unique solutions
+    #[arg(short, long, value_enum, default_value_t = ModeArg::Unique)]
+    mode: ModeArg,
+
+    /// Most solutions to count in count mode
+    #[arg(long, default_value_t = SolveMode::COUNT_LIMIT)]
+    limit: usize,
+
+    /// Write explanations of the logic backend as JSON
+    #[arg(long)]
+    json_explanation: bool,
+
+    /// Worker threads for batches, one per CPU if omitted
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    #[command(flatten)]
+    limits: LimitArgs,
+}
+
+#[derive(Args)]
+struct LimitArgs {
+    /// Seconds to spend on each puzzle before giving up
+    #[arg(long, value_parser = seconds)]
+    timeout: Option<Duration>,
+
+    /// z3 resource units each check may use before giving up, which unlike
+    /// a timeout gives the same result on any machine
+    #[arg(long)]
+    rlimit: Option<u32>,
+}
+
+impl LimitArgs {
+    fn limits(&self) -> Limits {
+        let mut limits = Limits::new();
+        if let Some(timeout) = self.timeout {
+            limits = limits.timeout(timeout);
+        }
+        if let Some(rlimit) = self.rlimit {
+            limits = limits.resource_limit(rlimit);
+        }
+        limits
+    }
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    #[command(flatten)]
+    output: OutputArgs,
+
+    /// Seed that makes the puzzles reproducible, taken from the clock if
+    /// omitted
+    #[arg(short, long)]
+    seed: Option<u64>,
+
+    /// Number of puzzles to generate
+    #[arg(short = 'n', long, default_value_t = 1)]
+    count: usize,
+
+    /// Symmetry of the givens
+    #[arg(long, value_enum, default_value_t = SymmetryArg::None)]
+    symmetry: SymmetryArg,
+
+    /// Puzzle file whose givens mark the only cells that may hold givens
+    #[arg(long)]
+    mask: Option<PathBuf>,
+
+    /// Easiest difficulty to accept
+    #[arg(short, long, value_enum)]
+    difficulty: Option<DifficultyArg>,
+
+    /// Hardest difficulty to accept, the same as --difficulty if omitted
+    #[arg(long, value_enum)]
+    max_difficulty: Option<DifficultyArg>,
+
+    /// Seconds to spend looking for each puzzle of the requested difficulty
+    #[arg(long, default_value_t = 60)]
+    budget: u64,
+}
+
+#[derive(Args)]
+struct ValidateArgs {
+    #[command(flatten)]
+    io: IoArgs,
+
+    /// Solver backend: z3, z3-onehot, z3-bitvec, native, dlx or logic
+    #[arg(short, long, default_value = "native", value_parser = backend)]
+    backend: &'static dyn Solver,
+}
+
+#[derive(Args)]
+struct Smt2Args {
+    #[command(flatten)]
+    io: IoArgs,
+
+    /// How to model the digit of each cell
+    #[arg(short, long, value_enum, default_value_t = EncodingArg::Int)]
+    encoding: EncodingArg,
+}
+
+#[derive(Args)]
+struct ModelArgs {
+    /// Output of the SAT solver
+    model: PathBuf,
+
+    /// File holding the puzzle the CNF was written for, stdin if omitted
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    #[command(flatten)]
+    io: IoArgs,
+
+    /// Backends to compare, separated by commas
+    #[arg(short, long, value_delimiter = ',', value_parser = backend)]
+    backend: Vec<&'static dyn Solver>,
+
+    #[command(flatten)]
+    limits: LimitArgs,
+}
+
+#[derive(Args)]
+struct PlayArgs {
+    /// File holding the puzzle to play, a new puzzle is generated if omitted
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    /// Seed for the generated puzzle, taken from the clock if omitted
+    #[arg(short, long)]
+    seed: Option<u64>,
+
+    /// Difficulty of the generated puzzle
+    #[arg(short, long, value_enum)]
+    difficulty: Option<DifficultyArg>,
+}
+
+fn backend(name: &str) -> Result<&'static dyn Solver, String> {
+    BACKENDS
+        .into_iter()
+        .find(|solver| solver.name() == name)
+        .ok_or_else(|| {
+            let names: Vec<_> = BACKENDS.iter().map(|solver| solver.name()).collect();
+            format!("expected one of {}", names.join(", "))
+        })
+}
+
+fn seconds(arg: &str) -> Result<Duration, String> {
+    let seconds: f64 = arg.parse().map_err(|e| format!("{e}"))?;
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("{e}"))
+}
+
+pub fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Some(Command::Solve(args)) => solve(args),
+        Some(Command::Generate(args)) => generate(args),
+        Some(Command::Rate(args)) => rate(args),
+        Some(Command::Validate(args)) => validate(args),
+        Some(Command::Convert(args)) => convert(args),
+        Some(Command::Edit(args)) => edit(args),
+        Some(Command::Play(args)) => play(args),
+        Some(Command::Bench(args)) => bench(args),
+        Some(Command::Cnf(args)) => cnf(args),
+        Some(Command::Model(args)) => model(args),
+        Some(Command::Smt2(args)) => smt2(args),
+        None if cli.solve.io.input.is_none() && io::stdin().is_terminal() => edit(cli.solve.io),
+        None => solve(cli.solve),
+    };
+
+    let exit = result.unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        match e {
+            InputError::Io(_) => Exit::Io,
+            InputError::Parse(_) => Exit::InvalidInput,
+        }
+    });
+
+    ExitCode::from(exit as u8)
+}
+
+/// Where a command writes its results.
+struct Output {
+    writer: Box<dyn Write>,
+    /// Whether the output is a terminal, where styles show rather than
+    /// leaving escape codes behind.
+    styled: bool,
+}
+
+impl Output {
+    /// Boxed table of `puzzle`, with solved cells highlighted if the output
+    /// is styled.
+    fn table(&self, puzzle: &Puzzle) -> String {
+        if !self.styled {
+            return puzzle.to_string();
+        }
+
+        let mut table = String::new();
+        puzzle
+            .write_table(&mut table, |cell, digit| {
+                if puzzle.is_given(cell) {
+                    digit.to_string().stylize()
+                } else {
+                    digit.to_string().blue().bold()
+                }
+            })
+            .unwrap();
+        table
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn open_output(path: Option<&Path>) -> io::Result<Output> {
+    Ok(match path {
+        Some(path) => Output {
+            writer: Box::new(BufWriter::new(File::create(path)?)),
+            styled: false,
+        },
+        None => Output {
+            writer: Box::new(io::stdout().lock()),
+            styled: io::stdout().is_terminal(),
+        },
+    })
+}
+
+fn solve(args: SolveArgs) -> Result<Exit, InputError> {
+    let puzzles = parse::read_puzzles(args.io.input.as_deref())?;
+    let mut out = open_output(args.io.output.output.as_deref())?;
+
+    let [parsed] = puzzles.as_slice() else {
+        let workers = args
+            .jobs
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, usize::from));
+        let as_json = args.io.output.format == Some(OutputFormat::Json);
+        let summary = batch::run(
+            &puzzles,
+            args.backend,
+            workers,
+            &args.limits.limits(),
+            as_json,
+            &mut out,
+        )?;
+        out.flush()?;
+        return Ok(Exit::from(&summary));
+    };
+
+    let format = args
+        .io
+        .output
+        .format
+        .unwrap_or_else(|| OutputFormat::matching(parsed));
+    let mode = match args.mode {
+        ModeArg::First => SolveMode::First,
+        ModeArg::Unique => SolveMode::Unique,
+        ModeArg::Count => SolveMode::Count(args.limit),
+    };
+
+    let puzzle = Puzzle::from_array(&parsed.grid);
+    let status = solve_one(
+        &puzzle,
+        mode,
+        args.backend,
+        &args.limits.limits(),
+        args.json_explanation,
+        &mut out,
+        format,
+    )?;
+    out.flush()?;
+
+    Ok(status.into())
+}
+
+/// Writes `puzzle` and its rating, then solves it. As JSON, writes a single
+/// object describing the result instead.
+fn solve_one(
+    puzzle: &Puzzle,
+    mode: SolveMode,
+    solver: &dyn Solver,
+    limits: &Limits,
+    explanation_json: bool,
+    out: &mut Output,
+    format: OutputFormat,
+) -> io::Result<Status> {
+    if format == OutputFormat::Json {
+        let outcome = batch::solve(puzzle, solver, mode.solution_limit(), limits);
+        writeln!(out, "{:#}", outcome.to_json(puzzle))?;
+        return Ok(outcome.status);
+    }
+
+    format.write_puzzle(out, puzzle)?;
+
+    if let Ok(rating) = rating::rate(&puzzle.to_array()) {
+        writeln!(out, "Difficulty: {rating}\n")?;
+    }
+
+    let outcome = match puzzle.solve(mode, solver, limits) {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            writeln!(out, "{e}")?;
+            return Ok(Status::Unsat);
+        }
+    };
+
+    match &outcome {
+        SolveOutcome::Solved {
+            solution,
+            explanation,
+        } => {
+            format.write_puzzle(out, solution)?;
+
+            if let Some(explanation) = explanation {
+                if explanation_json {
+                    writeln!(out, "{:#}", explanation.to_json())?;
+                } else {
+                    writeln!(out, "{explanation}")?;
+                }
+            }
+
+            if mode == SolveMode::Unique {
+                writeln!(out, "Solution is unique")?;
+            }
+        }
+        SolveOutcome::NotUnique(solution, other) => {
+            format.write_puzzle(out, solution)?;
+            writeln!(out, "Solution is not unique, another one is:")?;
+            format.write_puzzle(out, other)?;
+        }
+        SolveOutcome::Counted(count) if mode.solution_limit() <= *count => {
+            writeln!(out, "Found at least {count} solutions (limit reached)")?
+        }
+        SolveOutcome::Counted(count) => writeln!(out, "Found {count} solution(s)")?,
+        SolveOutcome::Unsatisfiable { contradiction } => {
+            writeln!(out, "No solution found")?;
+
+            if let Some(givens) = contradiction {
+                let givens: Vec<_> = givens
+                    .iter()
+                    .map(|(cell, digit)| format!("{cell}={digit}"))
+                    .collect();
+                writeln!(
+                    out,
+                    "These givens can't all be right: {}",
+                    givens.join(", ")
+                )?;
+            }
+        }
+        SolveOutcome::Unknown(UnknownReason::Timeout) => writeln!(out, "Solver timed out")?,
+        SolveOutcome::Unknown(reason) => writeln!(out, "Solver returned unknown: {reason}")?,
+    }
+
+    Ok(outcome.status())
+}
+
+fn generate(args: GenerateArgs) -> Result<Exit, InputError> {
+    let mask = match &args.mask {
+        Some(path) => Some(parse::read_puzzles(Some(path))?[0].grid),
+        None => None,
+    };
+
+    let band = match (
+        args.difficulty.map(Difficulty::from),
+        args.max_difficulty.map(Difficulty::from),
+    ) {
+        (None, None) => None,
+        (easiest, hardest) => {
+            let easiest = easiest.unwrap_or(Difficulty::Easy);
+            let hardest = hardest.unwrap_or(easiest);
+            if easiest > hardest {
+                eprintln!(
+                    "Error: --difficulty {easiest} is harder than --max-difficulty {hardest}"
+                );
+                return Ok(Exit::InvalidInput);
+            }
+            Some(easiest..=hardest)
+        }
+    };
+
+    let seed = args.seed.unwrap_or_else(generator::clock_seed);
+    eprintln!("Seed: {seed}");
+
+    let mut generator = Generator::new(seed).symmetry(args.symmetry.into());
+    if let Some(mask) = mask {
+        generator = generator.mask(mask.map(|row| row.map(|value| value != 0)));
+    }
+
+    let mut out = open_output(args.output.output.as_deref())?;
+    let format = args.output.format.unwrap_or(OutputFormat::Line);
+
+    for _ in 0..args.count {
+        let (grid, rating) = match &band {
+            Some(band) => {
+                let targeted =
+                    generator.generate_rated(band.clone(), Duration::from_secs(args.budget));
+                let Some(found) = targeted.found else {
+                    out.flush()?;
+                    eprintln!(
+                        "Error: no puzzle in the requested difficulty found in {} attempts",
+                        targeted.attempts
+                    );
+                    return Ok(Exit::Unknown);
+                };
+                eprintln!(
+                    "Attempts: {} in {:.2?}",
+                    targeted.attempts, targeted.elapsed
+                );
+                found
+            }
+            None => {
+                let Some(grid) = generator.generate() else {
+                    out.flush()?;
+                    eprintln!("Error: no puzzle with a unique solution fits the mask");
+                    return Ok(Exit::Unknown);
+                };
+                let rating = rating::rate(&grid).expect("generated puzzles are unique");
+                (grid, rating)
+            }
+        };
+
+        format.write_rated(&mut out, &Puzzle::from_array(&grid), &Ok(rating))?;
+    }
+
+    out.flush()?;
+    Ok(Exit::Success)
+}
+
+fn rate(args: IoArgs) -> Result<Exit, InputError> {
+    let puzzles = parse::read_puzzles(args.input.as_deref())?;
+    let mut out = open_output(args.output.output.as_deref())?;
+
+    let mut exit = Exit::Success;
+    for parsed in &puzzles {
+        let format = args
+            .output
+            .format
+            .unwrap_or_else(|| OutputFormat::matching(parsed));
+        let rating = rating::rate(&parsed.grid);
+        exit = exit.and(match rating {
+            Ok(_) => Exit::Success,
+            Err(RatingError::NoSolution) => Exit::Unsat,
+            Err(RatingError::NotUnique) => Exit::Multiple,
+        });
+
+        format.write_rated(&mut out, &Puzzle::from_array(&parsed.grid), &rating)?;
+    }
+
+    out.flush()?;
+    Ok(exit)
+}
+
+fn validate(args: ValidateArgs) -> Result<Exit, InputError> {
+    let puzzles = parse::read_puzzles(args.io.input.as_deref())?;
+    let mut out = open_output(args.io.output.output.as_deref())?;
+    let as_json = args.io.output.format == Some(OutputFormat::Json);
+
+    let mut summary = Summary::default();
+    for parsed in &puzzles {
+        let puzzle = Puzzle::from_array(&parsed.grid);
+        let outcome = batch::solve(&puzzle, args.backend, 2, &Limits::new());
+        summary.add(&outcome);
+
+        if as_json {
+            let mut result = outcome.to_json(&puzzle);
+            result["line"] = parsed.line.into();
+            writeln!(out, "{result}")?;
+            continue;
+        }
+
+        write!(out, "line {}: {}", parsed.line, outcome.status)?;
+        if !outcome.conflicts.is_empty() {
+            let conflicts: Vec<_> = outcome.conflicts.iter().map(ToString::to_string).collect();
+            write!(out, " ({})", conflicts.join("; "))?;
+        }
+        writeln!(out)?;
+    }
+
+    out.flush()?;
+    Ok(Exit::from(&summary))
+}
+
+/// Solves every puzzle with each backend in turn on this thread, so their
+/// times are comparable, then writes a row per backend.
+fn bench(args: BenchArgs) -> Result<Exit, InputError> {
+    let puzzles = parse::read_puzzles(args.io.input.as_deref())?;
+    let mut out = open_output(args.io.output.output.as_deref())?;
+    let as_json = args.io.output.format == Some(OutputFormat::Json);
+    let limits = args.limits.limits();
+
+    let z3 = Encoding::ALL.map(Z3Solver::new);
+    let solvers: Vec<&dyn Solver> = if args.backend.is_empty() {
+        z3.iter().map(|solver| solver as &dyn Solver).collect()
+    } else {
+        args.backend
+    };
+
+    if !as_json {
+        writeln!(
+            out,
+            "{:<10}  {:>7}  {:>7}  {:>8}  {:>7}  {:>12}  {:>12}  {:>12}",
+            "backend", "solved", "unsat", "multiple", "unknown", "total", "mean", "max"
+        )?;
+    }
+
+    for solver in solvers {
+        let mut summary = Summary::default();
+        let mut total = Duration::ZERO;
+        let mut slowest = Duration::ZERO;
+
+        for parsed in &puzzles {
+            let puzzle = Puzzle::from_array(&parsed.grid);
+            let outcome = batch::solve(&puzzle, solver, 2, &limits);
+            total += outcome.elapsed;
+            slowest = slowest.max(outcome.elapsed);
+            summary.add(&outcome);
+        }
+
+        let mean = total / puzzles.len() as u32;
+
+        if as_json {
+            let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+            let result = json!({
+                "backend": solver.name(),
+                "solved": summary.count(Status::Solved),
+                "unsat": summary.count(Status::Unsat),
+                "multiple": summary.count(Status::Multiple),
+                "unknown": summary.count(Status::Unknown),
+                "total_ms": ms(total),
+                "mean_ms": ms(mean),
+                "max_ms": ms(slowest),
+            });
+            writeln!(out, "{result}")?;
+            continue;
+        }
+
+        let ms = |duration: Duration| format!("{:.3} ms", duration.as_secs_f64() * 1000.0);
+        writeln!(
+            out,
+            "{:<10}  {:>7}  {:>7}  {:>8}  {:>7}  {:>12}  {:>12}  {:>12}",
+            solver.name(),
+            summary.count(Status::Solved),
+            summary.count(Status::Unsat),
+            summary.count(Status::Multiple),
+            summary.count(Status::Unknown),
+            ms(total),
+            ms(mean),
+            ms(slowest)
+        )?;
+    }
+
+    out.flush()?;
+    Ok(Exit::Success)
+}
+
+fn cnf(args: IoArgs) -> Result<Exit, InputError> {
+    let parsed = &parse::read_puzzles(args.input.as_deref())?[0];
+    let mut out = open_output(args.output.output.as_deref())?;
+
+    let puzzle = Puzzle::from_array(&parsed.grid);
+    let cnf = dimacs::to_cnf(&puzzle);
+    write_export(&mut out, args.output.format, &puzzle, "cnf", &cnf)?;
+    out.flush()?;
+    Ok(Exit::Success)
+}
+
+fn model(args: ModelArgs) -> Result<Exit, InputError> {
+    let parsed = &parse::read_puzzles(args.input.as_deref())?[0];
+    let output = fs::read_to_string(&args.model)?;
+
+    let puzzle = Puzzle::from_array(&parsed.grid);
+    let solution = match dimacs::read_model(&puzzle, &output) {
+        Ok(Some(solution)) => solution,
+        Ok(None) => {
+            eprintln!("The SAT solver found no solution");
+            return Ok(Exit::Unsat);
+        }
+        Err(ModelError::Unknown) => {
+            eprintln!("Error: {}", ModelError::Unknown);
+            return Ok(Exit::Unknown);
+        }
+        Err(e) => {
+            eprintln!("Error: invalid model: {e}");
+            return Ok(Exit::InvalidInput);
+        }
+    };
+
+    let mut out = open_output(args.output.output.as_deref())?;
+    let format = args
+        .output
+        .format
+        .unwrap_or_else(|| OutputFormat::matching(parsed));
+    format.write_puzzle(&mut out, &solution)?;
+    out.flush()?;
+    Ok(Exit::Success)
+}
+
+fn smt2(args: Smt2Args) -> Result<Exit, InputError> {
+    let parsed = &parse::read_puzzles(args.io.input.as_deref())?[0];
+    let mut out = open_output(args.io.output.output.as_deref())?;
+
+    let puzzle = Puzzle::from_array(&parsed.grid);
+    let smt2 = puzzle.to_smt2(args.encoding.into());
+    write_export(&mut out, args.io.output.format, &puzzle, "smt2", &smt2)?;
+    out.flush()?;
+    Ok(Exit::Success)
+}
+
+/// Writes `text`, an export of `puzzle`, as is, or as JSON an object holding
+/// the puzzle and the text under `key`.
+fn write_export(
+    out: &mut Output,
+    format: Option<OutputFormat>,
+    puzzle: &Puzzle,
+    key: &str,
+    text: &str,
+) -> io::Result<()> {
+    if format == Some(OutputFormat::Json) {
+        writeln!(out, "{}", json!({ "puzzle": puzzle.to_array(), key: text }))
+    } else {
+        out.write_all(text.as_bytes())
+    }
+}
+
+fn convert(args: IoArgs) -> Result<Exit, InputError> {
+    let puzzles = parse::read_puzzles(args.input.as_deref())?;
+    let mut out = open_output(args.output.output.as_deref())?;
+    let format = args.output.format.unwrap_or(OutputFormat::Line);
+
+    for parsed in &puzzles {
+        let puzzle = Puzzle::from_array(&parsed.grid);
+        match &parsed.comment {
+            Some(comment) => format.write_noted(&mut out, &puzzle, comment)?,
+            None => format.write_puzzle(&mut out, &puzzle)?,
+        }
+    }
+
+    out.flush()?;
+    Ok(Exit::Success)
+}
+
+fn edit(args: IoArgs) -> Result<Exit, InputError> {
+    let sudoku = match &args.input {
+        Some(path) => SudokuInput::with_grid(&parse::read_puzzles(Some(path))?[0].grid),
+        None => SudokuInput::new(),
+    };
+
+    let sudoku = crate::run(sudoku)?;
+
+    let mut out = open_output(args.output.output.as_deref())?;
+    let puzzle = Puzzle::from_array(&sudoku.to_array());
+    let status = solve_one(
+        &puzzle,
+        sudoku.solve_mode,
+        sudoku.solver(),
+        &Limits::new(),
+        sudoku.explanation_json,
+        &mut out,
+        args.output.format.unwrap_or_default(),
+    )?;
+    out.flush()?;
+
+    Ok(status.into())
+}
+
+fn play(args: PlayArgs) -> Result<Exit, InputError> {
+    let grid = match &args.input {
+        Some(path) => parse::read_puzzles(Some(path))?[0].grid,
+        None => {
+            let seed = args.seed.unwrap_or_else(generator::clock_seed);
+            eprintln!("Seed: {seed}");
+
+            let mut generator = Generator::new(seed);
+            let found = match args.difficulty.map(Difficulty::from) {
+                Some(difficulty) => generator
+                    .generate_rated(difficulty..=difficulty, Duration::from_secs(60))
+                    .found
+                    .map(|(grid, _)| grid),
+                None => generator.generate(),
+            };
+
+            let Some(grid) = found else {
+                eprintln!("Error: no puzzle in the requested difficulty found");
+                return Ok(Exit::Unknown);
+            };
+            grid
+        }
+    };
+
+    let solution = match native::solve_grid(&grid, 2).as_slice() {
+        [solution] => *solution,
+        [] => {
+            eprintln!("Error: the puzzle has no solution");
+            return Ok(Exit::Unsat);
+        }
+        _ => {
+            eprintln!("Error: the puzzle has more than one solution");
+            return Ok(Exit::Multiple);
+        }
+    };
+
+    let sudoku = crate::run(SudokuInput::play(&grid, solution))?;
+
+    let mut out = open_output(args.output.output.as_deref())?;
+    let format = args.output.format.unwrap_or_default();
+    let puzzle = Puzzle::from_array(&grid).with_values(&sudoku.to_array());
+    let mistakes = sudoku.play.as_ref().map_or(0, |play| play.mistakes);
+
+    let note = if sudoku.is_won() {
+        format!("Solved with {mistakes} mistakes")
+    } else {
+        format!("Unfinished, {mistakes} mistakes so far")
+    };
+    format.write_noted(&mut out, &puzzle, &note)?;
+    out.flush()?;
+
+    Ok(Exit::Success)
+}
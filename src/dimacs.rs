@@ -0,0 +1,282 @@
+//! DIMACS CNF, the input format of standalone SAT solvers such as kissat and
+//! CaDiCaL.
+//!
+//! A puzzle is written with the clauses of [`Encoding::OneHot`], the same
+//! ones the z3 backend asserts in that encoding, plus a unit clause per
+//! given. Variable `81 * row + 9 * col + digit`, with 0-based rows and
+//! columns, is true when that cell holds that digit; [`variable`] computes
+//! it and the header of the file lists every one.
+//!
+//! [`Encoding::OneHot`]: crate::Encoding::OneHot
+
+use std::{error, fmt, fmt::Write};
+
+use crate::{
+    Puzzle,
+    encoding::{self, Literal},
+    grid::{Cell, Conflict, Digit, Grid},
+};
+
+/// Number of variables, one per cell and digit.
+pub const VARIABLES: u32 = 729;
+
+/// The variable that is true when `cell` holds `digit`, from 1 to
+/// [`VARIABLES`].
+pub fn variable(cell: Cell, digit: Digit) -> u32 {
+    cell.index() as u32 * 9 + digit.get() as u32
+}
+
+/// The cell and digit of `variable`, or `None` if it's out of range.
+pub fn cell_digit(variable: u32) -> Option<(Cell, Digit)> {
+    let index = variable.checked_sub(1)?;
+    let cell = Cell::from_index(index as usize / 9)?;
+    Some((cell, Digit::new((index % 9) as u8 + 1)?))
+}
+
+/// Writes the rules and the filled cells of `puzzle` as a DIMACS CNF
+/// problem, headed by comments mapping every variable to its cell and digit.
+pub fn to_cnf(puzzle: &Puzzle) -> String {
+    let mut clauses = encoding::one_hot_rules();
+    for (cell, digit) in puzzle.grid().filled() {
+        clauses.push(vec![Literal {
+            cell,
+            digit,
+            holds: true,
+        }]);
+    }
+
+    let mut cnf = String::new();
+    writeln!(cnf, "c Sudoku {}", puzzle.to_line()).unwrap();
+    writeln!(
+        cnf,
+        "c Variable 81 * row + 9 * col + digit, with 0-based rows and columns, \
+         means that cell holds that digit:"
+    )
+    .unwrap();
+    for cell in Cell::all() {
+        for digit in Digit::ALL {
+            writeln!(cnf, "c {} {cell}={digit}", variable(cell, digit)).unwrap();
+        }
+    }
+
+    writeln!(cnf, "p cnf {VARIABLES} {}", clauses.len()).unwrap();
+    for clause in &clauses {
+        for literal in clause {
+            let variable = variable(literal.cell, literal.digit) as i64;
+            let value = if literal.holds { variable } else { -variable };
+            write!(cnf, "{value} ").unwrap();
+        }
+        writeln!(cnf, "0").unwrap();
+    }
+
+    cnf
+}
+
+/// Why a SAT solver's output couldn't be read as a solution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelError {
+    /// The solver didn't decide the problem.
+    Unknown,
+    /// A line that's neither a comment, a status nor literals.
+    Syntax { line: usize, text: String },
+    /// A literal of a variable that isn't in the problem.
+    OutOfRange { line: usize, literal: i64 },
+    /// No digit is true for `cell`.
+    Empty(Cell),
+    /// Several digits are true for `cell`.
+    Ambiguous(Cell),
+    /// The model puts `found` in a cell the puzzle filled with `expected`.
+    Changed {
+        cell: Cell,
+        expected: Digit,
+        found: Digit,
+    },
+    /// The model breaks the rules, so it's not of this puzzle's problem.
+    Conflict(Conflict),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::Unknown => write!(f, "the SAT solver didn't find an answer"),
+            ModelError::Syntax { line, text } => {
+                write!(f, "line {line}: expected literals, found {text:?}")
+            }
+            ModelError::OutOfRange { line, literal } => write!(
+                f,
+                "line {line}: literal {literal} isn't one of the {VARIABLES} variables"
+            ),
+            ModelError::Empty(cell) => write!(f, "no digit is true for {cell}"),
+            ModelError::Ambiguous(cell) => write!(f, "several digits are true for {cell}"),
+            ModelError::Changed {
+                cell,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{cell} is {found} in the model but {expected} in the puzzle"
+            ),
+            ModelError::Conflict(conflict) => write!(f, "the model breaks the rules: {conflict}"),
+        }
+    }
+}
+
+impl error::Error for ModelError {}
+
+/// Reads the output of a SAT solver run on [`to_cnf`] of `puzzle` back into
+/// the solved puzzle, or `None` if the solver found the problem
+/// unsatisfiable.
+///
+/// Both the competition format, with `s SATISFIABLE` and `v` lines, and the
+/// bare format of MiniSat, with `SAT` and a line of literals, are accepted.
+/// Lines starting with `c` are comments, and variables without a literal are
+/// false.
+pub fn read_model(puzzle: &Puzzle, output: &str) -> Result<Option<Puzzle>, ModelError> {
+    let mut values = [[0u8; 9]; 9];
+
+    for (number, text) in output.lines().enumerate() {
+        let line = number + 1;
+        let text = text.trim();
+
+        match text {
+            "" => continue,
+            _ if text.starts_with('c') => continue,
+            "s SATISFIABLE" | "SAT" | "SATISFIABLE" => continue,
+            "s UNSATISFIABLE" | "UNSAT" | "UNSATISFIABLE" => return Ok(None),
+            _ if text.starts_with('s') || text == "INDET" || text == "UNKNOWN" => {
+                return Err(ModelError::Unknown);
+            }
+            _ => {}
+        }
+
+        let literals = text.strip_prefix('v').unwrap_or(text);
+        for token in literals.split_whitespace() {
+            let literal: i64 = token.parse().map_err(|_| ModelError::Syntax {
+                line,
+                text: text.to_string(),
+            })?;
+            if literal <= 0 {
+                continue;
+            }
+
+            let (cell, digit) = u32::try_from(literal)
+                .ok()
+                .and_then(cell_digit)
+                .ok_or(ModelError::OutOfRange { line, literal })?;
+            let value = &mut values[cell.row()][cell.col()];
+            if *value != 0 {
+                return Err(ModelError::Ambiguous(cell));
+            }
+            *value = digit.get();
+        }
+    }
+
+    let grid = Grid::from_array(&values);
+    for (cell, digit) in grid.cells() {
+        let Some(found) = digit else {
+            return Err(ModelError::Empty(cell));
+        };
+        if let Some(expected) = puzzle.grid()[cell].filter(|&expected| expected != found) {
+            return Err(ModelError::Changed {
+                cell,
+                expected,
+                found,
+            });
+        }
+    }
+    if let Some(&conflict) = grid.conflicts().first() {
+        return Err(ModelError::Conflict(conflict));
+    }
+
+    Ok(Some(puzzle.with_values(&values)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{native, parse::parse_line};
+
+    const LINE: &str =
+        "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    /// What a SAT solver would print for `solution`, in the competition
+    /// format.
+    fn model(solution: &[[u8; 9]; 9]) -> String {
+        let mut output = String::from("c solved\ns SATISFIABLE\nv");
+        for cell in Cell::all() {
+            for digit in Digit::ALL {
+                let variable = variable(cell, digit) as i64;
+                let holds = solution[cell.row()][cell.col()] == digit.get();
+                write!(output, " {}", if holds { variable } else { -variable }).unwrap();
+            }
+        }
+        output.push_str(" 0\n");
+        output
+    }
+
+    #[test]
+    fn variables_round_trip() {
+        for variable in 1..=VARIABLES {
+            let (cell, digit) = cell_digit(variable).unwrap();
+            assert_eq!(super::variable(cell, digit), variable);
+        }
+        assert_eq!(cell_digit(0), None);
+        assert_eq!(cell_digit(VARIABLES + 1), None);
+    }
+
+    #[test]
+    fn solution_satisfies_the_cnf_and_reads_back() {
+        let (grid, _) = parse_line(LINE).unwrap();
+        let puzzle = Puzzle::from_array(&grid);
+        let solution = native::solve_grid(&grid, 1)[0];
+        let output = model(&solution);
+
+        // Every clause has a true literal under the solution
+        let cnf = to_cnf(&puzzle);
+        let header = cnf.lines().find(|line| line.starts_with("p cnf")).unwrap();
+        let clauses: Vec<&str> = cnf
+            .lines()
+            .filter(|line| !line.starts_with(['c', 'p']))
+            .collect();
+        assert_eq!(header, format!("p cnf {VARIABLES} {}", clauses.len()));
+        let true_literals: HashSet<&str> = output
+            .split_whitespace()
+            .filter(|&token| token != "0")
+            .collect();
+        for clause in clauses {
+            assert!(
+                clause
+                    .split_whitespace()
+                    .any(|literal| true_literals.contains(literal)),
+                "clause {clause:?} is false"
+            );
+        }
+
+        let read = read_model(&puzzle, &output).unwrap().unwrap();
+        assert_eq!(read.to_array(), solution);
+        assert_eq!(read.givens(), puzzle.givens());
+    }
+
+    #[test]
+    fn models_of_another_puzzle_are_rejected() {
+        let (grid, _) = parse_line(LINE).unwrap();
+        let solution = native::solve_grid(&grid, 1)[0];
+
+        let mut other = grid;
+        other[0][2] = if solution[0][2] == 1 { 2 } else { 1 };
+        let cell = Cell::new(0, 2).unwrap();
+        assert_eq!(
+            read_model(&Puzzle::from_array(&other), &model(&solution)).unwrap_err(),
+            ModelError::Changed {
+                cell,
+                expected: Digit::new(other[0][2]).unwrap(),
+                found: Digit::new(solution[0][2]).unwrap(),
+            }
+        );
+
+        let puzzle = Puzzle::from_array(&grid);
+        assert!(matches!(read_model(&puzzle, "s UNSATISFIABLE\n"), Ok(None)));
+    }
+}
@@ -0,0 +1,306 @@
+//! Exact-cover solver backend using Knuth's Dancing Links (Algorithm X).
+//!
+//! A sudoku is an exact cover problem over 324 constraint columns: every cell
+//! holds one digit, and every row, column and box holds each digit once. Each
+//! of the 729 possible placements covers exactly four of those columns.
+//! Variants only need extra columns, so the matrix itself knows nothing about
+//! sudoku.
+
+use std::time::Instant;
+
+use crate::{Found, Limits, Puzzle, Solver, UnknownReason, solver::Search};
+
+/// Sparse 0/1 matrix stored as circular doubly linked lists, which makes
+/// removing and restoring a column during the search cheap.
+///
+/// Node 0 is the root, nodes `1..=columns` are the column headers and every
+/// node after that is a 1 in some row.
+pub struct ExactCover {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    /// Column header of each node.
+    column: Vec<usize>,
+    /// Row id of each node.
+    row: Vec<usize>,
+    /// Number of nodes still linked into each column, indexed by header.
+    size: Vec<usize>,
+    /// First node of each row, indexed by row id.
+    row_start: Vec<usize>,
+}
+
+impl ExactCover {
+    /// Creates a matrix with `columns` columns and no rows.
+    pub fn new(columns: usize) -> Self {
+        let headers = columns + 1;
+
+        Self {
+            left: (0..headers).map(|i| (i + headers - 1) % headers).collect(),
+            right: (0..headers).map(|i| (i + 1) % headers).collect(),
+            up: (0..headers).collect(),
+            down: (0..headers).collect(),
+            column: (0..headers).collect(),
+            row: vec![usize::MAX; headers],
+            size: vec![0; headers],
+            row_start: Vec::new(),
+        }
+    }
+
+    /// Adds a row with a 1 in each of `columns` and returns its id.
+    pub fn add_row(&mut self, columns: &[usize]) -> usize {
+        let id = self.row_start.len();
+        let first = self.left.len();
+        self.row_start.push(first);
+
+        for (i, &col) in columns.iter().enumerate() {
+            let header = col + 1;
+            let node = first + i;
+
+            self.left.push(if i == 0 {
+                first + columns.len() - 1
+            } else {
+                node - 1
+            });
+            self.right.push(if i + 1 == columns.len() {
+                first
+            } else {
+                node + 1
+            });
+
+            // Append to the bottom of the column
+            self.up.push(self.up[header]);
+            self.down.push(header);
+            let last = self.up[header];
+            self.down[last] = node;
+            self.up[header] = node;
+
+            self.column.push(header);
+            self.row.push(id);
+            self.size[header] += 1;
+        }
+
+        id
+    }
+
+    /// Forces row `id` into the solution, as is done for givens. Returns
+    /// `false` if it clashes with a row selected earlier.
+    pub fn select(&mut self, id: usize) -> bool {
+        let first = self.row_start[id];
+
+        let nodes = self.row_nodes(first);
+
+        if !nodes.iter().all(|&node| self.is_active(self.column[node])) {
+            return false;
+        }
+
+        for node in nodes {
+            self.cover(self.column[node]);
+        }
+
+        true
+    }
+
+    /// Returns at most `limit` exact covers of the remaining columns, each as
+    /// the ids of the rows it uses (not including selected rows).
+    pub fn solve(&mut self, limit: usize) -> Vec<Vec<usize>> {
+        self.solve_within(limit, &Limits::new()).0
+    }
+
+    /// Like [`ExactCover::solve`], but stops once `limits` are exceeded and
+    /// then also returns why.
+    pub fn solve_within(
+        &mut self,
+        limit: usize,
+        limits: &Limits,
+    ) -> (Vec<Vec<usize>>, Option<UnknownReason>) {
+        let mut search = Search::new(limit, limits, Instant::now());
+
+        if limit > 0 {
+            self.search(&mut search, &mut Vec::new());
+        }
+
+        (search.solutions, search.unknown)
+    }
+
+    /// Extends `partial`, the rows chosen on the way down to this node, into
+    /// covers collected in `search`.
+    fn search(&mut self, search: &mut Search<Vec<usize>>, partial: &mut Vec<usize>) {
+        if self.right[0] == 0 {
+            search.solutions.push(partial.clone());
+            return;
+        }
+
+        // Branch on the column with the fewest rows left
+        let mut col = self.right[0];
+        let mut best = col;
+        while col != 0 {
+            if self.size[col] < self.size[best] {
+                best = col;
+            }
+            col = self.right[col];
+        }
+
+        self.cover(best);
+
+        let mut node = self.down[best];
+        while node != best && !search.is_done() {
+            partial.push(self.row[node]);
+
+            let mut other = self.right[node];
+            while other != node {
+                self.cover(self.column[other]);
+                other = self.right[other];
+            }
+
+            self.search(search, partial);
+
+            let mut other = self.left[node];
+            while other != node {
+                self.uncover(self.column[other]);
+                other = self.left[other];
+            }
+
+            partial.pop();
+            node = self.down[node];
+        }
+
+        self.uncover(best);
+    }
+
+    fn is_active(&self, header: usize) -> bool {
+        self.right[self.left[header]] == header
+    }
+
+    /// Returns the nodes of the row starting at `first`.
+    fn row_nodes(&self, first: usize) -> Vec<usize> {
+        let mut nodes = vec![first];
+        let mut node = self.right[first];
+        while node != first {
+            nodes.push(node);
+            node = self.right[node];
+        }
+        nodes
+    }
+
+    /// Unlinks `header` and every row that has a 1 in it.
+    fn cover(&mut self, header: usize) {
+        self.right[self.left[header]] = self.right[header];
+        self.left[self.right[header]] = self.left[header];
+
+        let mut node = self.down[header];
+        while node != header {
+            let mut other = self.right[node];
+            while other != node {
+                self.down[self.up[other]] = self.down[other];
+                self.up[self.down[other]] = self.up[other];
+                self.size[self.column[other]] -= 1;
+                other = self.right[other];
+            }
+            node = self.down[node];
+        }
+    }
+
+    /// Undoes `cover(header)`, relinking in the reverse order.
+    fn uncover(&mut self, header: usize) {
+        let mut node = self.up[header];
+        while node != header {
+            let mut other = self.left[node];
+            while other != node {
+                self.size[self.column[other]] += 1;
+                self.down[self.up[other]] = other;
+                self.up[self.down[other]] = other;
+                other = self.left[other];
+            }
+            node = self.up[node];
+        }
+
+        self.right[self.left[header]] = header;
+        self.left[self.right[header]] = header;
+    }
+}
+
+/// Row id of the placement of `digit` at `row`, `col`.
+fn placement(row: usize, col: usize, digit: u8) -> usize {
+    row * 81 + col * 9 + (digit as usize - 1)
+}
+
+/// Builds the 729 x 324 exact cover matrix of a standard sudoku.
+fn sudoku_matrix() -> ExactCover {
+    let mut matrix = ExactCover::new(324);
+
+    for row in 0..9 {
+        for col in 0..9 {
+            for digit in 0..9 {
+                let box_index = row / 3 * 3 + col / 3;
+                matrix.add_row(&[
+                    row * 9 + col,
+                    81 + row * 9 + digit,
+                    162 + col * 9 + digit,
+                    243 + box_index * 9 + digit,
+                ]);
+            }
+        }
+    }
+
+    matrix
+}
+
+/// Returns at most `limit` solutions of `grid`, where 0 marks an empty cell.
+pub fn solve_grid(grid: &[[u8; 9]; 9], limit: usize) -> Vec<[[u8; 9]; 9]> {
+    solve_grid_within(grid, limit, &Limits::new()).0
+}
+
+/// Returns at most `limit` solutions of `grid`, where 0 marks an empty cell,
+/// along with why the search stopped early if `limits` cut it short.
+pub fn solve_grid_within(
+    grid: &[[u8; 9]; 9],
+    limit: usize,
+    limits: &Limits,
+) -> (Vec<[[u8; 9]; 9]>, Option<UnknownReason>) {
+    let mut matrix = sudoku_matrix();
+
+    for (row, values) in grid.iter().enumerate() {
+        for (col, &value) in values.iter().enumerate() {
+            if matches!(value, 1..=9) && !matrix.select(placement(row, col, value)) {
+                return (Vec::new(), None);
+            }
+        }
+    }
+
+    let (covers, unknown) = matrix.solve_within(limit, limits);
+    let solutions = covers
+        .into_iter()
+        .map(|rows| {
+            let mut solution = *grid;
+            for id in rows {
+                solution[id / 81][id / 9 % 9] = (id % 9) as u8 + 1;
+            }
+            solution
+        })
+        .collect();
+
+    (solutions, unknown)
+}
+
+/// Solves puzzles as an exact cover problem with Dancing Links.
+pub struct DlxSolver;
+
+impl Solver for DlxSolver {
+    fn name(&self) -> &'static str {
+        "dlx"
+    }
+
+    fn find_solutions(&self, puzzle: &Puzzle, limit: usize, limits: &Limits) -> Found {
+        let (solutions, unknown) = solve_grid_within(&puzzle.to_array(), limit, limits);
+
+        Found {
+            solutions: solutions
+                .iter()
+                .map(|grid| puzzle.with_values(grid))
+                .collect(),
+            unknown,
+            explanation: None,
+        }
+    }
+}
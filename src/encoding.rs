@@ -0,0 +1,217 @@
+//! The ways the sudoku rules can be written down for z3.
+//!
+//! z3 gets very different problems depending on how a cell is modelled:
+//!
+//! - [`Encoding::Int`]: an integer per cell, bounded to 1..=9, with
+//!   `distinct` over every unit. Short, but it leaves z3 to reason about
+//!   arithmetic.
+//! - [`Encoding::OneHot`]: a boolean per cell and digit, with exactly one
+//!   true per cell and exactly one per digit in every unit. Every constraint
+//!   is a clause, so z3 solves it as plain SAT.
+//! - [`Encoding::BitVec`]: a 4-bit bit-vector per cell, bounded to 1..=9,
+//!   with `distinct` over every unit, which z3 bit-blasts into SAT.
+//!
+//! Which is fastest depends on the puzzles; `sudoku_solver bench` compares
+//! them on a puzzle file.
+
+use std::fmt;
+
+use z3::{
+    Model,
+    ast::{Ast, BV, Bool, Int},
+};
+
+use crate::grid::{self, Cell, Digit};
+
+/// Bits in the bit-vector of a cell, enough for 1 to 9.
+const BITS: u32 = 4;
+
+/// How the z3 backend models the digit of a cell.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// An integer per cell
+    #[default]
+    Int,
+    /// A boolean per cell and digit
+    OneHot,
+    /// A 4-bit bit-vector per cell
+    BitVec,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 3] = [Encoding::Int, Encoding::OneHot, Encoding::BitVec];
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Encoding::Int => "int",
+            Encoding::OneHot => "one-hot",
+            Encoding::BitVec => "bit-vec",
+        })
+    }
+}
+
+/// The z3 variables of every cell in some encoding, indexed by
+/// [`Cell::index`].
+pub(crate) enum CellVars {
+    Int(Vec<Int>),
+    /// The variable of digit `d` is at index `d - 1`.
+    OneHot(Vec<[Bool; 9]>),
+    BitVec(Vec<BV>),
+}
+
+impl CellVars {
+    /// Fresh variables for every cell, constrained by nothing yet.
+    pub(crate) fn new(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Int => CellVars::Int(
+                Cell::all()
+                    .map(|cell| Int::new_const(cell.to_string()))
+                    .collect(),
+            ),
+            Encoding::OneHot => CellVars::OneHot(
+                Cell::all()
+                    .map(|cell| Digit::ALL.map(|digit| Bool::new_const(format!("{cell}#{digit}"))))
+                    .collect(),
+            ),
+            Encoding::BitVec => CellVars::BitVec(
+                Cell::all()
+                    .map(|cell| BV::new_const(cell.to_string(), BITS))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Asserts the sudoku rules on `solver`.
+    pub(crate) fn assert_rules(&self, solver: &z3::Solver) {
+        match self {
+            CellVars::Int(vars) => {
+                for var in vars {
+                    solver.assert(var.ge(Int::from_u64(1)));
+                    solver.assert(var.le(Int::from_u64(9)));
+                }
+                for unit in grid::units() {
+                    solver.assert(Int::distinct(&unit.map(|cell| &vars[cell.index()])));
+                }
+            }
+            CellVars::OneHot(_) => {
+                for clause in one_hot_rules() {
+                    let literals: Vec<Bool> = clause
+                        .iter()
+                        .map(|literal| {
+                            let holds = self.holds(literal.cell, literal.digit);
+                            if literal.holds { holds } else { holds.not() }
+                        })
+                        .collect();
+                    solver.assert(Bool::or(&literals));
+                }
+            }
+            CellVars::BitVec(vars) => {
+                for var in vars {
+                    solver.assert(BV::from_u64(1, BITS).bvule(var));
+                    solver.assert(var.bvule(BV::from_u64(9, BITS)));
+                }
+                for unit in grid::units() {
+                    solver.assert(BV::distinct(&unit.map(|cell| &vars[cell.index()])));
+                }
+            }
+        }
+    }
+
+    /// The formula saying that `cell` holds `digit`.
+    pub(crate) fn holds(&self, cell: Cell, digit: Digit) -> Bool {
+        match self {
+            CellVars::Int(vars) => vars[cell.index()].eq(Int::from_u64(digit.get() as u64)),
+            CellVars::OneHot(vars) => vars[cell.index()][digit.get() as usize - 1].clone(),
+            CellVars::BitVec(vars) => vars[cell.index()].eq(BV::from_u64(digit.get() as u64, BITS)),
+        }
+    }
+
+    /// The digit `model` puts in `cell`, if any.
+    pub(crate) fn value(&self, model: &Model, cell: Cell) -> Option<Digit> {
+        let value = match self {
+            CellVars::Int(vars) => model.eval(&vars[cell.index()], true)?.as_u64()?,
+            CellVars::OneHot(vars) => {
+                let index = vars[cell.index()].iter().position(|var| {
+                    model.eval(var, true).and_then(|v| v.as_bool()) == Some(true)
+                })?;
+                index as u64 + 1
+            }
+            CellVars::BitVec(vars) => model.eval(&vars[cell.index()], true)?.as_u64()?,
+        };
+        Digit::new(value as u8)
+    }
+}
+
+/// A literal of the one-hot encoding: that `cell` holds `digit`, or with
+/// `holds` false, that it doesn't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Literal {
+    pub cell: Cell,
+    pub digit: Digit,
+    pub holds: bool,
+}
+
+/// The sudoku rules in the one-hot encoding, as clauses any of whose
+/// literals must hold: exactly one digit per cell, and each digit exactly
+/// once in every row, column and box.
+pub(crate) fn one_hot_rules() -> Vec<Vec<Literal>> {
+    let mut clauses = Vec::new();
+
+    for cell in Cell::all() {
+        exactly_one(&mut clauses, Digit::ALL.map(|digit| (cell, digit)));
+    }
+    for unit in grid::units() {
+        for digit in Digit::ALL {
+            exactly_one(&mut clauses, unit.map(|cell| (cell, digit)));
+        }
+    }
+
+    clauses
+}
+
+/// Adds clauses saying that exactly one of `choices` holds: one saying at
+/// least one does and one per pair saying not both do.
+fn exactly_one(clauses: &mut Vec<Vec<Literal>>, choices: [(Cell, Digit); 9]) {
+    let literal = |(cell, digit), holds| Literal { cell, digit, holds };
+
+    clauses.push(choices.map(|choice| literal(choice, true)).to_vec());
+    for (i, &first) in choices.iter().enumerate() {
+        for &second in &choices[i + 1..] {
+            clauses.push(vec![literal(first, false), literal(second, false)]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Limits, Puzzle, parse::parse_line};
+
+    fn count(line: &str, encoding: Encoding) -> usize {
+        let puzzle = Puzzle::from_array(&parse_line(line).unwrap().0);
+        puzzle
+            .count_solutions(50, encoding, &Limits::new())
+            .unwrap()
+    }
+
+    #[test]
+    fn encodings_count_the_same_solutions() {
+        for (line, solutions) in [
+            (
+                "5...7....6...95....9.....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+                10,
+            ),
+            // No two givens conflict, but there is no solution
+            (
+                "82.........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..",
+                0,
+            ),
+        ] {
+            for encoding in Encoding::ALL {
+                assert_eq!(count(line, encoding), solutions, "{encoding} on {line}");
+            }
+        }
+    }
+}
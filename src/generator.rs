@@ -0,0 +1,347 @@
+//! Random puzzle generation.
+//!
+//! A random full grid is built first, then clues are removed in random order,
+//! putting them back if the puzzle stops having a unique solution. Removing
+//! clues can only add solutions, so once every clue has been tried the puzzle
+//! is minimal: no remaining clue can be removed.
+//!
+//! Clues are removed together with their images under the requested
+//! [`Symmetry`], so the givens keep that symmetry. A mask can additionally
+//! restrict which cells may hold givens at all.
+//!
+//! For a target difficulty, whole puzzles are generated and rated until one
+//! falls within the requested band.
+
+use std::{
+    fmt,
+    ops::RangeInclusive,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use rand::{SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    native,
+    rating::{self, Difficulty, Rating},
+};
+
+/// Number of full grids tried before giving up on a mask that doesn't allow
+/// a unique puzzle.
+const MASK_ATTEMPTS: usize = 20;
+
+/// Symmetry of the givens' layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    None,
+    /// Unchanged by a half turn about the centre.
+    Rotational,
+    /// Mirrored across the main diagonal.
+    Diagonal,
+    /// Mirrored across the middle row.
+    Horizontal,
+    /// Mirrored across the middle column.
+    Vertical,
+    /// Unchanged by every rotation and reflection of the square.
+    Dihedral,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 6] = [
+        Symmetry::None,
+        Symmetry::Rotational,
+        Symmetry::Diagonal,
+        Symmetry::Horizontal,
+        Symmetry::Vertical,
+        Symmetry::Dihedral,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Symmetry::None => "none",
+            Symmetry::Rotational => "rotational",
+            Symmetry::Diagonal => "diagonal",
+            Symmetry::Horizontal => "horizontal",
+            Symmetry::Vertical => "vertical",
+            Symmetry::Dihedral => "dihedral",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Symmetry::ALL.iter().position(|&s| s == self).unwrap_or(0);
+        Symmetry::ALL[(index + 1) % Symmetry::ALL.len()]
+    }
+
+    /// Cells (`row * 9 + col`) that `cell` maps to, including itself.
+    pub fn orbit(self, cell: usize) -> Vec<usize> {
+        let (r, c) = (cell / 9, cell % 9);
+        let images = match self {
+            Symmetry::None => vec![(r, c)],
+            Symmetry::Rotational => vec![(r, c), (8 - r, 8 - c)],
+            Symmetry::Diagonal => vec![(r, c), (c, r)],
+            Symmetry::Horizontal => vec![(r, c), (8 - r, c)],
+            Symmetry::Vertical => vec![(r, c), (r, 8 - c)],
+            Symmetry::Dihedral => vec![
+                (r, c),
+                (c, 8 - r),
+                (8 - r, 8 - c),
+                (8 - c, r),
+                (8 - r, c),
+                (r, 8 - c),
+                (c, r),
+                (8 - c, 8 - r),
+            ],
+        };
+
+        let mut orbit: Vec<usize> = images.into_iter().map(|(r, c)| r * 9 + c).collect();
+        orbit.sort_unstable();
+        orbit.dedup();
+        orbit
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Returns a seed taken from the system clock. Callers should show it, so the
+/// puzzles can be generated again.
+pub fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+/// Generates puzzles from a seeded random number generator, so the same seed
+/// and settings always produce the same sequence of puzzles.
+pub struct Generator {
+    rng: ChaCha8Rng,
+    symmetry: Symmetry,
+    mask: Option<[[bool; 9]; 9]>,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            symmetry: Symmetry::None,
+            mask: None,
+        }
+    }
+
+    /// Keeps the givens symmetric under `symmetry`.
+    pub fn symmetry(mut self, symmetry: Symmetry) -> Self {
+        self.symmetry = symmetry;
+        self
+    }
+
+    /// Only allows givens where `mask` is `true`.
+    pub fn mask(mut self, mask: [[bool; 9]; 9]) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Returns a random completely filled grid.
+    pub fn full_grid(&mut self) -> [[u8; 9]; 9] {
+        // The boxes on the diagonal don't constrain each other, so fill them
+        // with random permutations and let the solver complete the rest
+        let mut grid = [[0u8; 9]; 9];
+        for band in 0..3 {
+            let mut digits: Vec<u8> = (1..=9).collect();
+            digits.shuffle(&mut self.rng);
+
+            for (i, digit) in digits.into_iter().enumerate() {
+                grid[band * 3 + i / 3][band * 3 + i % 3] = digit;
+            }
+        }
+
+        native::solve_grid(&grid, 1)[0]
+    }
+
+    /// Returns a random minimal puzzle with a unique solution, with 0 marking
+    /// empty cells. Returns `None` if the mask leaves too few cells for a
+    /// unique solution.
+    pub fn generate(&mut self) -> Option<[[u8; 9]; 9]> {
+        let attempts = if self.mask.is_some() {
+            MASK_ATTEMPTS
+        } else {
+            1
+        };
+
+        (0..attempts).find_map(|_| self.attempt(|_| true))
+    }
+
+    /// Keeps generating puzzles until one is rated within `band`, or `budget`
+    /// runs out.
+    ///
+    /// Clues that would make the puzzle harder than the band are kept, so
+    /// easy targets end up with more givens instead of being rejected; such
+    /// puzzles are minimal only among those within the band.
+    pub fn generate_rated(
+        &mut self,
+        band: RangeInclusive<Difficulty>,
+        budget: Duration,
+    ) -> Targeted {
+        let start = Instant::now();
+        let mut attempts = 0;
+
+        let hardest = *band.end();
+        let within_band =
+            |grid: &[[u8; 9]; 9]| rating::rate(grid).is_ok_and(|r| r.difficulty <= hardest);
+
+        while start.elapsed() < budget {
+            attempts += 1;
+
+            let Some(grid) = self.attempt(within_band) else {
+                continue;
+            };
+
+            if let Ok(rating) = rating::rate(&grid)
+                && band.contains(&rating.difficulty)
+            {
+                return Targeted {
+                    found: Some((grid, rating)),
+                    attempts,
+                    elapsed: start.elapsed(),
+                };
+            }
+        }
+
+        Targeted {
+            found: None,
+            attempts,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /// Generates a full grid, applies the mask and removes clues as long as
+    /// the solution stays unique and `accept` holds. Returns `None` if the
+    /// masked grid isn't uniquely solvable.
+    fn attempt(&mut self, accept: impl Fn(&[[u8; 9]; 9]) -> bool) -> Option<[[u8; 9]; 9]> {
+        let mut grid = self.full_grid();
+
+        if let Some(mask) = &self.mask {
+            for (row, allowed) in mask.iter().enumerate() {
+                for (col, &allowed) in allowed.iter().enumerate() {
+                    if !allowed {
+                        grid[row][col] = 0;
+                    }
+                }
+            }
+
+            if !is_unique(&grid) {
+                return None;
+            }
+        }
+
+        let mut cells: Vec<usize> = (0..81).collect();
+        cells.shuffle(&mut self.rng);
+
+        // Remove one symmetry orbit at a time
+        for cell in cells {
+            if grid[cell / 9][cell % 9] == 0 {
+                continue;
+            }
+
+            let orbit = self.symmetry.orbit(cell);
+            let values: Vec<u8> = orbit.iter().map(|&i| grid[i / 9][i % 9]).collect();
+
+            for &i in &orbit {
+                grid[i / 9][i % 9] = 0;
+            }
+
+            if !is_unique(&grid) || !accept(&grid) {
+                for (&i, &value) in orbit.iter().zip(&values) {
+                    grid[i / 9][i % 9] = value;
+                }
+            }
+        }
+
+        Some(grid)
+    }
+}
+
+/// Outcome of [`Generator::generate_rated`].
+pub struct Targeted {
+    /// The puzzle and its rating, or `None` if the budget ran out first.
+    pub found: Option<([[u8; 9]; 9], Rating)>,
+    /// Number of puzzles generated, including the one returned.
+    pub attempts: usize,
+    pub elapsed: Duration,
+}
+
+fn is_unique(grid: &[[u8; 9]; 9]) -> bool {
+    native::solve_grid(grid, 2).len() == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cells (`row * 9 + col`) holding a given.
+    fn givens(grid: &[[u8; 9]; 9]) -> Vec<usize> {
+        (0..81).filter(|&i| grid[i / 9][i % 9] != 0).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_puzzle() {
+        for seed in [0, 1, 42] {
+            assert_eq!(
+                Generator::new(seed).generate(),
+                Generator::new(seed).generate()
+            );
+        }
+        assert_ne!(Generator::new(1).generate(), Generator::new(2).generate());
+    }
+
+    #[test]
+    fn puzzles_are_unique_and_minimal() {
+        let mut generator = Generator::new(7);
+
+        for _ in 0..3 {
+            let grid = generator.generate().unwrap();
+            assert!(is_unique(&grid));
+
+            for cell in givens(&grid) {
+                let mut fewer = grid;
+                fewer[cell / 9][cell % 9] = 0;
+                assert!(!is_unique(&fewer), "given {cell} can be removed");
+            }
+        }
+    }
+
+    #[test]
+    fn givens_keep_the_symmetry() {
+        for symmetry in Symmetry::ALL {
+            let grid = Generator::new(3).symmetry(symmetry).generate().unwrap();
+            assert!(is_unique(&grid));
+
+            for cell in givens(&grid) {
+                for image in symmetry.orbit(cell) {
+                    assert_ne!(
+                        grid[image / 9][image % 9],
+                        0,
+                        "{symmetry}: given {cell} has no image at {image}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn givens_stay_within_the_mask() {
+        // No givens on the main diagonal
+        let mut mask = [[true; 9]; 9];
+        for (i, row) in mask.iter_mut().enumerate() {
+            row[i] = false;
+        }
+
+        let grid = Generator::new(5).mask(mask).generate().unwrap();
+        assert!(is_unique(&grid));
+        for cell in givens(&grid) {
+            assert!(mask[cell / 9][cell % 9], "given {cell} is outside the mask");
+        }
+    }
+}
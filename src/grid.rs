@@ -0,0 +1,348 @@
+//! Typed cells, digits and grids.
+//!
+//! Rows, columns and boxes are numbered from 0 here, with boxes in reading
+//! order. Cells display 1-based, as `R3C5`, matching the explanations.
+
+use std::{
+    fmt,
+    num::NonZeroU8,
+    ops::{Index, IndexMut},
+};
+
+/// Cell indices (`row * 9 + col`) of every row, column and box.
+pub const UNITS: [[usize; 9]; 27] = build_units();
+
+/// Cell indices sharing a row, column or box with each cell.
+pub const PEERS: [[usize; 20]; 81] = build_peers();
+
+const fn build_units() -> [[usize; 9]; 27] {
+    let mut units = [[0; 9]; 27];
+    let mut i = 0;
+    while i < 9 {
+        let mut j = 0;
+        while j < 9 {
+            units[i][j] = i * 9 + j;
+            units[9 + i][j] = j * 9 + i;
+            units[18 + i][j] = (i / 3 * 3 + j / 3) * 9 + i % 3 * 3 + j % 3;
+            j += 1;
+        }
+        i += 1;
+    }
+    units
+}
+
+const fn build_peers() -> [[usize; 20]; 81] {
+    let mut peers = [[0; 20]; 81];
+    let mut cell = 0;
+    while cell < 81 {
+        let (row, col) = (cell / 9, cell % 9);
+        let mut n = 0;
+        let mut other = 0;
+        while other < 81 {
+            let (r, c) = (other / 9, other % 9);
+            let same_box = r / 3 == row / 3 && c / 3 == col / 3;
+            if other != cell && (r == row || c == col || same_box) {
+                peers[cell][n] = other;
+                n += 1;
+            }
+            other += 1;
+        }
+        cell += 1;
+    }
+    peers
+}
+
+/// A cell of the grid. The default is the top left cell.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cell {
+    row: u8,
+    col: u8,
+}
+
+impl Cell {
+    /// The cell at `row`, `col`, or `None` if either is 9 or more.
+    pub fn new(row: usize, col: usize) -> Option<Self> {
+        (row < 9 && col < 9).then_some(Self {
+            row: row as u8,
+            col: col as u8,
+        })
+    }
+
+    /// The cell at `index`, counting in reading order (`row * 9 + col`).
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::new(index / 9, index % 9)
+    }
+
+    /// Every cell, in reading order.
+    pub fn all() -> impl Iterator<Item = Cell> {
+        (0..81).map(Self::at)
+    }
+
+    pub fn row(self) -> usize {
+        self.row as usize
+    }
+
+    pub fn col(self) -> usize {
+        self.col as usize
+    }
+
+    /// Index of the box holding this cell.
+    pub fn box_index(self) -> usize {
+        self.row() / 3 * 3 + self.col() / 3
+    }
+
+    /// Position in reading order, as used by [`Cell::from_index`].
+    pub fn index(self) -> usize {
+        self.row() * 9 + self.col()
+    }
+
+    /// The 20 other cells sharing a row, column or box with this one.
+    pub fn peers(self) -> impl Iterator<Item = Cell> {
+        PEERS[self.index()].into_iter().map(Self::at)
+    }
+
+    /// Whether `other` is a different cell sharing a row, column or box.
+    pub fn sees(self, other: Cell) -> bool {
+        self != other
+            && (self.row == other.row
+                || self.col == other.col
+                || self.box_index() == other.box_index())
+    }
+
+    /// The cell at an index known to be in range.
+    pub(crate) fn at(index: usize) -> Self {
+        Self {
+            row: (index / 9) as u8,
+            col: (index % 9) as u8,
+        }
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "R{}C{}", self.row + 1, self.col + 1)
+    }
+}
+
+/// The cells of every row, column and box, in that order.
+pub fn units() -> impl Iterator<Item = [Cell; 9]> {
+    UNITS.into_iter().map(|unit| unit.map(Cell::at))
+}
+
+/// One of the digits 1 to 9.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Digit(NonZeroU8);
+
+impl Digit {
+    pub const ALL: [Digit; 9] = {
+        let mut all = [Digit(NonZeroU8::MIN); 9];
+        let mut i = 0;
+        while i < 9 {
+            all[i] = Digit(NonZeroU8::MIN.saturating_add(i as u8));
+            i += 1;
+        }
+        all
+    };
+
+    /// The digit `value`, or `None` unless it's 1 to 9.
+    pub fn new(value: u8) -> Option<Self> {
+        NonZeroU8::new(value).filter(|_| value <= 9).map(Self)
+    }
+
+    pub fn get(self) -> u8 {
+        self.0.get()
+    }
+}
+
+impl fmt::Display for Digit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The contents of every cell, some of which may be empty.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Grid {
+    cells: [[Option<Digit>; 9]; 9],
+}
+
+impl Grid {
+    /// A grid with every cell empty.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The grid holding `values`, where anything but 1 to 9 marks an empty
+    /// cell.
+    pub fn from_array(values: &[[u8; 9]; 9]) -> Self {
+        let mut grid = Self::new();
+        for cell in Cell::all() {
+            grid[cell] = Digit::new(values[cell.row()][cell.col()]);
+        }
+        grid
+    }
+
+    /// The values of the grid, with 0 for empty cells.
+    pub fn to_array(&self) -> [[u8; 9]; 9] {
+        self.cells
+            .map(|row| row.map(|digit| digit.map_or(0, Digit::get)))
+    }
+
+    /// Every cell and its digit, in reading order.
+    pub fn cells(&self) -> impl Iterator<Item = (Cell, Option<Digit>)> + '_ {
+        Cell::all().map(|cell| (cell, self[cell]))
+    }
+
+    /// The filled cells and their digits, in reading order.
+    pub fn filled(&self) -> impl Iterator<Item = (Cell, Digit)> + '_ {
+        Cell::all().filter_map(|cell| Some((cell, self[cell]?)))
+    }
+
+    /// Every pair of cells that see each other and hold the same digit, in
+    /// reading order of the first cell.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        self.filled()
+            .flat_map(|(cell, digit)| {
+                self.peers(cell)
+                    .filter(move |&(peer, other)| peer > cell && other == Some(digit))
+                    .map(move |(peer, _)| Conflict {
+                        cells: [cell, peer],
+                        digit,
+                    })
+            })
+            .collect()
+    }
+
+    /// Whether every cell holds a digit.
+    pub fn is_full(&self) -> bool {
+        self.cells.iter().flatten().all(Option::is_some)
+    }
+
+    /// The cells of `row` and their digits. Panics if `row` is 9 or more.
+    pub fn row(&self, row: usize) -> impl Iterator<Item = (Cell, Option<Digit>)> + '_ {
+        self.unit(UNITS[..9][row])
+    }
+
+    /// The cells of `col` and their digits. Panics if `col` is 9 or more.
+    pub fn column(&self, col: usize) -> impl Iterator<Item = (Cell, Option<Digit>)> + '_ {
+        self.unit(UNITS[9..18][col])
+    }
+
+    /// The cells of box `index` and their digits, with boxes numbered in
+    /// reading order. Panics if `index` is 9 or more.
+    pub fn in_box(&self, index: usize) -> impl Iterator<Item = (Cell, Option<Digit>)> + '_ {
+        self.unit(UNITS[18..][index])
+    }
+
+    /// The peers of `cell` and their digits.
+    pub fn peers(&self, cell: Cell) -> impl Iterator<Item = (Cell, Option<Digit>)> + '_ {
+        cell.peers().map(|peer| (peer, self[peer]))
+    }
+
+    fn unit(&self, cells: [usize; 9]) -> impl Iterator<Item = (Cell, Option<Digit>)> + '_ {
+        cells.into_iter().map(|index| {
+            let cell = Cell::at(index);
+            (cell, self[cell])
+        })
+    }
+}
+
+/// Two cells that see each other holding the same digit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Conflict {
+    pub cells: [Cell; 2],
+    pub digit: Digit,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [first, second] = self.cells;
+        write!(f, "{first} and {second} both {}", self.digit)
+    }
+}
+
+impl Index<Cell> for Grid {
+    type Output = Option<Digit>;
+
+    fn index(&self, cell: Cell) -> &Option<Digit> {
+        &self.cells[cell.row()][cell.col()]
+    }
+}
+
+impl IndexMut<Cell> for Grid {
+    fn index_mut(&mut self, cell: Cell) -> &mut Option<Digit> {
+        &mut self.cells[cell.row()][cell.col()]
+    }
+}
+
+impl From<[[u8; 9]; 9]> for Grid {
+    fn from(values: [[u8; 9]; 9]) -> Self {
+        Self::from_array(&values)
+    }
+}
+
+impl From<Grid> for [[u8; 9]; 9] {
+    fn from(grid: Grid) -> Self {
+        grid.to_array()
+    }
+}
+
+/// Bitmask with bits 1 through 9 set.
+pub const ALL_DIGITS: u16 = 0b11_1111_1110;
+
+/// The working grid of the native search and the logical solver: placed
+/// digits, and the candidates of every empty cell as a bitmask with bit `d`
+/// set while digit `d` is still possible.
+#[derive(Clone)]
+pub(crate) struct Board {
+    /// Placed digit of each cell, 0 if empty.
+    pub(crate) cells: [u8; 81],
+    /// Remaining candidates of each empty cell.
+    pub(crate) candidates: [u16; 81],
+}
+
+impl Board {
+    /// Places the givens of `grid`, returning `None` if two of them conflict.
+    pub(crate) fn from_array(grid: &[[u8; 9]; 9]) -> Option<Self> {
+        let mut board = Board {
+            cells: [0; 81],
+            candidates: [ALL_DIGITS; 81],
+        };
+
+        for (row, values) in grid.iter().enumerate() {
+            for (col, &value) in values.iter().enumerate() {
+                if matches!(value, 1..=9) && !board.place(row * 9 + col, value) {
+                    return None;
+                }
+            }
+        }
+
+        Some(board)
+    }
+
+    pub(crate) fn to_array(&self) -> [[u8; 9]; 9] {
+        let mut result = [[0u8; 9]; 9];
+        for (cell, &value) in self.cells.iter().enumerate() {
+            result[cell / 9][cell % 9] = value;
+        }
+        result
+    }
+
+    /// Puts `digit` in `cell` and removes it from the candidates of its
+    /// peers. Returns `false`, changing nothing, if `digit` isn't a
+    /// candidate of `cell`.
+    pub(crate) fn place(&mut self, cell: usize, digit: u8) -> bool {
+        let bit = 1 << digit;
+        if self.candidates[cell] & bit == 0 {
+            return false;
+        }
+
+        self.cells[cell] = digit;
+        self.candidates[cell] = 0;
+        for &peer in &PEERS[cell] {
+            self.candidates[peer] &= !bit;
+        }
+
+        true
+    }
+}
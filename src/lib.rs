@@ -0,0 +1,23 @@
+//! Sudoku solving with interchangeable backends: z3, a bitmask backtracker,
+//! dancing links and a logical solver that explains its steps the way a person
+//! would. Also rates and generates puzzles and reads them from text.
+
+pub mod batch;
+pub mod dimacs;
+pub mod dlx;
+pub mod encoding;
+pub mod generator;
+pub mod grid;
+pub mod logic;
+pub mod native;
+pub mod parse;
+pub mod puzzle;
+pub mod rating;
+pub mod solver;
+
+pub use encoding::Encoding;
+pub use puzzle::{Puzzle, Solutions};
+pub use solver::{
+    BACKENDS, CancelToken, Found, Limits, SolveError, SolveMode, SolveOutcome, Solver, Status,
+    UnknownReason, Z3Solver,
+};
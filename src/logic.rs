@@ -0,0 +1,927 @@
+//! Human-style solver backend.
+//!
+//! Works on a grid of pencil marks and only ever applies one named technique
+//! at a time, always picking the simplest one that makes progress, so that
+//! every step can be explained to a person. When no technique applies the
+//! rest of the grid is handed to z3.
+
+use std::fmt;
+
+use serde_json::json;
+
+use crate::{
+    Found, Limits, Puzzle, Solver, Z3Solver,
+    grid::{Board, Cell, PEERS, UNITS},
+};
+
+/// A named solving technique.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Technique {
+    HiddenSingle,
+    NakedSingle,
+    PointingPair,
+    BoxLineReduction,
+    NakedPair,
+    XWing,
+    HiddenPair,
+    NakedTriple,
+    Swordfish,
+    HiddenTriple,
+    XyWing,
+    XyzWing,
+    NakedQuad,
+    HiddenQuad,
+    SimpleColouring,
+}
+
+impl Technique {
+    /// Every technique, from the simplest to the most involved.
+    pub const ALL: [Technique; 15] = [
+        Technique::HiddenSingle,
+        Technique::NakedSingle,
+        Technique::PointingPair,
+        Technique::BoxLineReduction,
+        Technique::NakedPair,
+        Technique::XWing,
+        Technique::HiddenPair,
+        Technique::NakedTriple,
+        Technique::Swordfish,
+        Technique::HiddenTriple,
+        Technique::XyWing,
+        Technique::XyzWing,
+        Technique::NakedQuad,
+        Technique::HiddenQuad,
+        Technique::SimpleColouring,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Technique::HiddenSingle => "hidden single",
+            Technique::NakedSingle => "naked single",
+            Technique::PointingPair => "pointing pair",
+            Technique::BoxLineReduction => "box/line reduction",
+            Technique::NakedPair => "naked pair",
+            Technique::XWing => "X-Wing",
+            Technique::HiddenPair => "hidden pair",
+            Technique::NakedTriple => "naked triple",
+            Technique::Swordfish => "Swordfish",
+            Technique::HiddenTriple => "hidden triple",
+            Technique::XyWing => "XY-Wing",
+            Technique::XyzWing => "XYZ-Wing",
+            Technique::SimpleColouring => "simple colouring",
+            Technique::NakedQuad => "naked quad",
+            Technique::HiddenQuad => "hidden quad",
+        }
+    }
+
+    /// Looks for one application of this technique on `grid`.
+    fn find(self, grid: &Board) -> Option<Deduction> {
+        match self {
+            Technique::HiddenSingle => grid.hidden_single(),
+            Technique::NakedSingle => grid.naked_single(),
+            Technique::PointingPair => grid.pointing(),
+            Technique::BoxLineReduction => grid.box_line_reduction(),
+            Technique::NakedPair => grid.naked_subset(2, self),
+            Technique::NakedTriple => grid.naked_subset(3, self),
+            Technique::NakedQuad => grid.naked_subset(4, self),
+            Technique::HiddenPair => grid.hidden_subset(2, self),
+            Technique::HiddenTriple => grid.hidden_subset(3, self),
+            Technique::HiddenQuad => grid.hidden_subset(4, self),
+            Technique::XWing => grid.fish(2, self),
+            Technique::Swordfish => grid.fish(3, self),
+            Technique::XyWing => grid.xy_wing(),
+            Technique::XyzWing => grid.xyz_wing(),
+            Technique::SimpleColouring => grid.simple_colouring(),
+        }
+    }
+}
+
+impl fmt::Display for Technique {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One application of a technique. Cells are indexed `row * 9 + col`.
+#[derive(Debug, Clone)]
+pub struct Deduction {
+    pub technique: Technique,
+    /// Cells solved by this step, with their digit.
+    pub placements: Vec<(usize, u8)>,
+    /// Candidates removed by this step.
+    pub eliminations: Vec<(usize, u8)>,
+    /// Where the technique was spotted, e.g. `"in box 2"`.
+    pub detail: String,
+}
+
+impl fmt::Display for Deduction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = self
+            .placements
+            .iter()
+            .map(|&(cell, digit)| format!("{} = {digit}", Cell::at(cell)))
+            .collect();
+
+        // One clause per eliminated digit, keeping the order they were found in
+        let mut eliminated: Vec<u8> = Vec::new();
+        for &(_, digit) in &self.eliminations {
+            if !eliminated.contains(&digit) {
+                eliminated.push(digit);
+            }
+        }
+        for digit in eliminated {
+            let cells: Vec<usize> = self
+                .eliminations
+                .iter()
+                .filter(|&&(_, d)| d == digit)
+                .map(|&(cell, _)| cell)
+                .collect();
+            parts.push(format!("eliminate {digit} from {}", cell_names(&cells)));
+        }
+
+        write!(f, "{} by {}", parts.join("; "), self.technique)?;
+        if !self.detail.is_empty() {
+            write!(f, " {}", self.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// How far the techniques got on their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogicResult {
+    Solved,
+    /// No technique applies to the remaining grid.
+    Stalled,
+    /// The deductions emptied a cell or unit, so there is no solution.
+    Contradiction,
+}
+
+/// The deductions made on a puzzle, in the order they were applied.
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub deductions: Vec<Deduction>,
+    pub result: LogicResult,
+}
+
+impl Explanation {
+    /// Serialises the steps for consumption by other tools. Rows and columns
+    /// are numbered from 1, as in the text form.
+    pub fn to_json(&self) -> serde_json::Value {
+        let marks = |marks: &[(usize, u8)]| -> Vec<serde_json::Value> {
+            marks
+                .iter()
+                .map(|&(cell, digit)| {
+                    json!({ "row": cell / 9 + 1, "col": cell % 9 + 1, "digit": digit })
+                })
+                .collect()
+        };
+
+        json!({
+            "result": match self.result {
+                LogicResult::Solved => "solved",
+                LogicResult::Stalled => "stalled",
+                LogicResult::Contradiction => "contradiction",
+            },
+            "steps": self
+                .deductions
+                .iter()
+                .map(|deduction| {
+                    json!({
+                        "technique": deduction.technique.name(),
+                        "description": deduction.to_string(),
+                        "placements": marks(&deduction.placements),
+                        "eliminations": marks(&deduction.eliminations),
+                    })
+                })
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// Re-applies the steps to `grid`, the puzzle they were found on,
+    /// yielding each step with the placed digits once it has been applied.
+    pub fn replay(&self, grid: &[[u8; 9]; 9]) -> impl Iterator<Item = (&Deduction, [[u8; 9]; 9])> {
+        let mut grid = Board::from_array(grid);
+
+        self.deductions.iter().map_while(move |deduction| {
+            let grid = grid.as_mut()?;
+            grid.apply(deduction);
+            Some((deduction, grid.to_array()))
+        })
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, deduction) in self.deductions.iter().enumerate() {
+            writeln!(f, "{:>3}. {deduction}", i + 1)?;
+        }
+
+        match self.result {
+            LogicResult::Solved => writeln!(f, "Solved by logic alone"),
+            LogicResult::Stalled => writeln!(
+                f,
+                "Logic stalled after {} steps, z3 finished the grid",
+                self.deductions.len()
+            ),
+            LogicResult::Contradiction => writeln!(f, "Logic ran into a contradiction"),
+        }
+    }
+}
+
+/// Solves puzzles step by step with named techniques, falling back to z3
+/// when none of them applies.
+pub struct LogicalSolver;
+
+impl Solver for LogicalSolver {
+    fn name(&self) -> &'static str {
+        "logic"
+    }
+
+    fn find_solutions(&self, puzzle: &Puzzle, limit: usize, limits: &Limits) -> Found {
+        let Some(mut grid) = Board::from_array(&puzzle.to_array()) else {
+            return Found {
+                solutions: Vec::new(),
+                unknown: None,
+                explanation: None,
+            };
+        };
+
+        let explanation = grid.solve();
+        let partial = puzzle.with_values(&grid.to_array());
+
+        match explanation.result {
+            // Every technique is sound, so a grid solved by logic has exactly
+            // one solution
+            LogicResult::Solved => Found {
+                solutions: std::iter::once(partial).take(limit).collect(),
+                unknown: None,
+                explanation: Some(explanation),
+            },
+            LogicResult::Contradiction => Found {
+                solutions: Vec::new(),
+                unknown: None,
+                explanation: Some(explanation),
+            },
+            LogicResult::Stalled => Found {
+                explanation: Some(explanation),
+                ..Z3Solver::default().find_solutions(&partial, limit, limits)
+            },
+        }
+    }
+}
+
+/// Name of `UNITS[index]`, e.g. `"box 2"`.
+fn unit_name(index: usize) -> String {
+    let kind = ["row", "column", "box"][index / 9];
+    format!("{kind} {}", index % 9 + 1)
+}
+
+/// Comma separated names of `cells`.
+fn cell_names(cells: &[usize]) -> String {
+    cells
+        .iter()
+        .map(|&cell| Cell::at(cell).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Runs the techniques on `grid`, where 0 marks an empty cell, without
+/// falling back to z3. Returns `None` if two givens conflict.
+pub fn explain(grid: &[[u8; 9]; 9]) -> Option<Explanation> {
+    Some(Board::from_array(grid)?.solve())
+}
+
+fn bit(digit: u8) -> u16 {
+    1 << digit
+}
+
+/// Digits whose bits are set in `mask`, in increasing order.
+fn digits(mask: u16) -> impl Iterator<Item = u8> {
+    (1..=9).filter(move |&digit| mask & bit(digit) != 0)
+}
+
+/// All `k`-element subsets of `items`, keeping their order.
+fn combinations<T: Copy>(items: &[T], k: usize) -> Vec<Vec<T>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    if items.len() < k {
+        return Vec::new();
+    }
+
+    let mut result: Vec<Vec<T>> = combinations(&items[1..], k - 1)
+        .into_iter()
+        .map(|mut rest| {
+            rest.insert(0, items[0]);
+            rest
+        })
+        .collect();
+    result.extend(combinations(&items[1..], k));
+    result
+}
+
+/// The techniques, working on the pencil marks of a [`Board`].
+impl Board {
+    fn apply(&mut self, deduction: &Deduction) {
+        for &(cell, digit) in &deduction.placements {
+            self.place(cell, digit);
+        }
+        for &(cell, digit) in &deduction.eliminations {
+            self.candidates[cell] &= !bit(digit);
+        }
+    }
+
+    /// Applies techniques until the grid is solved, broken or stuck.
+    fn solve(&mut self) -> Explanation {
+        let mut deductions = Vec::new();
+
+        let result = loop {
+            if self.is_broken() {
+                break LogicResult::Contradiction;
+            }
+            if self.cells.iter().all(|&value| value != 0) {
+                break LogicResult::Solved;
+            }
+
+            let Some(deduction) = Technique::ALL
+                .iter()
+                .find_map(|technique| technique.find(self))
+            else {
+                break LogicResult::Stalled;
+            };
+
+            self.apply(&deduction);
+            deductions.push(deduction);
+        };
+
+        Explanation { deductions, result }
+    }
+
+    /// Whether some empty cell has no candidates, or some digit has no place
+    /// left in a unit.
+    fn is_broken(&self) -> bool {
+        let empty_cell = (0..81).any(|cell| self.cells[cell] == 0 && self.candidates[cell] == 0);
+
+        let missing_digit = UNITS.iter().any(|unit| {
+            (1..=9).any(|digit| {
+                unit.iter().all(|&cell| self.cells[cell] != digit)
+                    && self.positions(unit, digit).is_empty()
+            })
+        });
+
+        empty_cell || missing_digit
+    }
+
+    /// Empty cells of `unit` that still have `digit` as a candidate.
+    fn positions(&self, unit: &[usize], digit: u8) -> Vec<usize> {
+        unit.iter()
+            .copied()
+            .filter(|&cell| self.candidates[cell] & bit(digit) != 0)
+            .collect()
+    }
+
+    /// Builds a deduction removing `digit` from each of `cells` that still
+    /// has it, or `None` if none do.
+    fn eliminate(
+        &self,
+        technique: Technique,
+        digit: u8,
+        cells: impl IntoIterator<Item = usize>,
+        detail: String,
+    ) -> Option<Deduction> {
+        let eliminations: Vec<(usize, u8)> = cells
+            .into_iter()
+            .filter(|&cell| self.candidates[cell] & bit(digit) != 0)
+            .map(|cell| (cell, digit))
+            .collect();
+
+        (!eliminations.is_empty()).then_some(Deduction {
+            technique,
+            placements: Vec::new(),
+            eliminations,
+            detail,
+        })
+    }
+
+    fn hidden_single(&self) -> Option<Deduction> {
+        // Boxes first, which is where people usually spot them
+        for index in (18..27).chain(0..18) {
+            for digit in 1..=9 {
+                if let [cell] = self.positions(&UNITS[index], digit)[..] {
+                    return Some(Deduction {
+                        technique: Technique::HiddenSingle,
+                        placements: vec![(cell, digit)],
+                        eliminations: Vec::new(),
+                        detail: format!("in {}", unit_name(index)),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn naked_single(&self) -> Option<Deduction> {
+        (0..81)
+            .find(|&cell| self.candidates[cell].count_ones() == 1)
+            .map(|cell| Deduction {
+                technique: Technique::NakedSingle,
+                placements: vec![(cell, self.candidates[cell].trailing_zeros() as u8)],
+                eliminations: Vec::new(),
+                detail: String::new(),
+            })
+    }
+
+    /// A digit confined to one row or column within a box can be removed
+    /// from the rest of that line.
+    fn pointing(&self) -> Option<Deduction> {
+        for (index, unit) in UNITS.iter().enumerate().skip(18) {
+            for digit in 1..=9 {
+                let positions = self.positions(unit, digit);
+                if positions.len() < 2 {
+                    continue;
+                }
+
+                let line = if positions
+                    .iter()
+                    .all(|&c| Cell::at(c).row() == Cell::at(positions[0]).row())
+                {
+                    &UNITS[Cell::at(positions[0]).row()]
+                } else if positions
+                    .iter()
+                    .all(|&c| Cell::at(c).col() == Cell::at(positions[0]).col())
+                {
+                    &UNITS[9 + Cell::at(positions[0]).col()]
+                } else {
+                    continue;
+                };
+
+                let others = line.iter().copied().filter(|c| !unit.contains(c));
+                let detail = format!("in {}", unit_name(index));
+                if let Some(deduction) =
+                    self.eliminate(Technique::PointingPair, digit, others, detail)
+                {
+                    return Some(deduction);
+                }
+            }
+        }
+        None
+    }
+
+    /// A digit confined to one box within a row or column can be removed
+    /// from the rest of that box.
+    fn box_line_reduction(&self) -> Option<Deduction> {
+        for (index, unit) in UNITS.iter().enumerate().take(18) {
+            for digit in 1..=9 {
+                let positions = self.positions(unit, digit);
+                if positions.len() < 2
+                    || !positions
+                        .iter()
+                        .all(|&c| Cell::at(c).box_index() == Cell::at(positions[0]).box_index())
+                {
+                    continue;
+                }
+
+                let others = UNITS[18 + Cell::at(positions[0]).box_index()]
+                    .iter()
+                    .copied()
+                    .filter(|c| !unit.contains(c));
+                let detail = format!("in {}", unit_name(index));
+                if let Some(deduction) =
+                    self.eliminate(Technique::BoxLineReduction, digit, others, detail)
+                {
+                    return Some(deduction);
+                }
+            }
+        }
+        None
+    }
+
+    /// `size` cells of a unit whose candidates together are only `size`
+    /// digits; those digits can go nowhere else in the unit.
+    fn naked_subset(&self, size: usize, technique: Technique) -> Option<Deduction> {
+        for (index, unit) in UNITS.iter().enumerate() {
+            let cells: Vec<usize> = unit
+                .iter()
+                .copied()
+                .filter(|&cell| (2..=size as u32).contains(&self.candidates[cell].count_ones()))
+                .collect();
+
+            for subset in combinations(&cells, size) {
+                let union = subset
+                    .iter()
+                    .fold(0, |mask, &cell| mask | self.candidates[cell]);
+                if union.count_ones() as usize != size {
+                    continue;
+                }
+
+                let eliminations: Vec<(usize, u8)> = unit
+                    .iter()
+                    .filter(|cell| !subset.contains(cell))
+                    .flat_map(|&cell| digits(self.candidates[cell] & union).map(move |d| (cell, d)))
+                    .collect();
+
+                if !eliminations.is_empty() {
+                    return Some(Deduction {
+                        technique,
+                        placements: Vec::new(),
+                        eliminations,
+                        detail: format!("on {} in {}", cell_names(&subset), unit_name(index)),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// `size` digits of a unit that only fit in the same `size` cells; those
+    /// cells can hold nothing else.
+    fn hidden_subset(&self, size: usize, technique: Technique) -> Option<Deduction> {
+        for (index, unit) in UNITS.iter().enumerate() {
+            let unit_digits: Vec<u8> = (1..=9)
+                .filter(|&digit| (2..=size).contains(&self.positions(unit, digit).len()))
+                .collect();
+
+            for subset in combinations(&unit_digits, size) {
+                let mut cells: Vec<usize> = subset
+                    .iter()
+                    .flat_map(|&digit| self.positions(unit, digit))
+                    .collect();
+                cells.sort_unstable();
+                cells.dedup();
+
+                if cells.len() != size {
+                    continue;
+                }
+
+                let keep = subset.iter().fold(0, |mask, &digit| mask | bit(digit));
+                let eliminations: Vec<(usize, u8)> = cells
+                    .iter()
+                    .flat_map(|&cell| digits(self.candidates[cell] & !keep).map(move |d| (cell, d)))
+                    .collect();
+
+                if !eliminations.is_empty() {
+                    let digit_list: Vec<String> = subset.iter().map(u8::to_string).collect();
+                    return Some(Deduction {
+                        technique,
+                        placements: Vec::new(),
+                        eliminations,
+                        detail: format!(
+                            "of {{{}}} on {} in {}",
+                            digit_list.join(", "),
+                            cell_names(&cells),
+                            unit_name(index)
+                        ),
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// X-Wing (`size` 2) and Swordfish (`size` 3): a digit confined to the
+    /// same `size` columns in `size` rows can be removed from the rest of
+    /// those columns, and likewise with rows and columns swapped.
+    fn fish(&self, size: usize, technique: Technique) -> Option<Deduction> {
+        for digit in 1..=9 {
+            for (base, cover) in [(0, 9), (9, 0)] {
+                // Base lines holding the digit in 2..=size places, along with
+                // the cover lines those places are in as a bitmask
+                let lines: Vec<(usize, u16)> = (0..9)
+                    .map(|i| {
+                        let covers = self
+                            .positions(&UNITS[base + i], digit)
+                            .iter()
+                            .map(|&cell| {
+                                if base == 0 {
+                                    Cell::at(cell).col()
+                                } else {
+                                    Cell::at(cell).row()
+                                }
+                            })
+                            .fold(0, |mask, index| mask | 1 << index);
+                        (i, covers)
+                    })
+                    .filter(|&(_, covers): &(usize, u16)| {
+                        (2..=size as u32).contains(&covers.count_ones())
+                    })
+                    .collect();
+
+                for subset in combinations(&lines, size) {
+                    let covers = subset.iter().fold(0, |mask, &(_, covers)| mask | covers);
+                    if covers.count_ones() as usize != size {
+                        continue;
+                    }
+
+                    let others = (0..9)
+                        .filter(|&i| covers & 1 << i != 0)
+                        .flat_map(|i| UNITS[cover + i])
+                        .filter(|&cell| {
+                            let line = if base == 0 {
+                                Cell::at(cell).row()
+                            } else {
+                                Cell::at(cell).col()
+                            };
+                            subset.iter().all(|&(i, _)| i != line)
+                        });
+
+                    let lines: Vec<String> =
+                        subset.iter().map(|(i, _)| (i + 1).to_string()).collect();
+                    let detail = format!(
+                        "on {digit} in {} {}",
+                        if base == 0 { "rows" } else { "columns" },
+                        lines.join(", ")
+                    );
+                    if let Some(deduction) = self.eliminate(technique, digit, others, detail) {
+                        return Some(deduction);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// A bivalue pivot `xy` seeing pincers `xz` and `yz`: whichever value
+    /// the pivot takes, one pincer is `z`, so cells seeing both pincers can't
+    /// be `z`.
+    fn xy_wing(&self) -> Option<Deduction> {
+        for (pivot, peers) in PEERS.iter().enumerate() {
+            let pivot_mask = self.candidates[pivot];
+            if pivot_mask.count_ones() != 2 {
+                continue;
+            }
+
+            for &first in peers {
+                let first_mask = self.candidates[first];
+                if first_mask.count_ones() != 2 || (first_mask & pivot_mask).count_ones() != 1 {
+                    continue;
+                }
+
+                let z = first_mask & !pivot_mask;
+                let second_mask = (pivot_mask & !first_mask) | z;
+
+                for &second in peers {
+                    if second == first || self.candidates[second] != second_mask {
+                        continue;
+                    }
+
+                    let digit = z.trailing_zeros() as u8;
+                    let others = (0..81).filter(|&cell| {
+                        cell != pivot
+                            && Cell::at(cell).sees(Cell::at(first))
+                            && Cell::at(cell).sees(Cell::at(second))
+                    });
+                    let detail = format!(
+                        "with pivot {} and pincers {}",
+                        Cell::at(pivot),
+                        cell_names(&[first, second])
+                    );
+                    if let Some(deduction) =
+                        self.eliminate(Technique::XyWing, digit, others, detail)
+                    {
+                        return Some(deduction);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// A pivot `xyz` seeing pincers `xz` and `yz`: one of the three is `z`,
+    /// so cells seeing all of them can't be `z`.
+    fn xyz_wing(&self) -> Option<Deduction> {
+        for (pivot, peers) in PEERS.iter().enumerate() {
+            let pivot_mask = self.candidates[pivot];
+            if pivot_mask.count_ones() != 3 {
+                continue;
+            }
+
+            let pincers: Vec<usize> = peers
+                .iter()
+                .copied()
+                .filter(|&cell| {
+                    let mask = self.candidates[cell];
+                    mask.count_ones() == 2 && mask & !pivot_mask == 0
+                })
+                .collect();
+
+            for pair in combinations(&pincers, 2) {
+                let (first, second) = (pair[0], pair[1]);
+                let (first_mask, second_mask) = (self.candidates[first], self.candidates[second]);
+                if first_mask | second_mask != pivot_mask || first_mask == second_mask {
+                    continue;
+                }
+
+                let digit = (first_mask & second_mask).trailing_zeros() as u8;
+                let others = (0..81).filter(|&cell| {
+                    Cell::at(cell).sees(Cell::at(pivot))
+                        && Cell::at(cell).sees(Cell::at(first))
+                        && Cell::at(cell).sees(Cell::at(second))
+                });
+                let detail = format!(
+                    "with pivot {} and pincers {}",
+                    Cell::at(pivot),
+                    cell_names(&[first, second])
+                );
+                if let Some(deduction) = self.eliminate(Technique::XyzWing, digit, others, detail) {
+                    return Some(deduction);
+                }
+            }
+        }
+        None
+    }
+
+    /// Colours chains of conjugate pairs (a digit with exactly two places in
+    /// a unit) alternately. If two cells of one colour see each other that
+    /// colour is false; any cell seeing both colours can't hold the digit.
+    fn simple_colouring(&self) -> Option<Deduction> {
+        for digit in 1..=9 {
+            let mut links: Vec<Vec<usize>> = vec![Vec::new(); 81];
+            for unit in &UNITS {
+                if let [a, b] = self.positions(unit, digit)[..] {
+                    links[a].push(b);
+                    links[b].push(a);
+                }
+            }
+
+            let mut colour: [Option<bool>; 81] = [None; 81];
+            for start in 0..81 {
+                if links[start].is_empty() || colour[start].is_some() {
+                    continue;
+                }
+
+                // Colour the chain containing `start`
+                let mut chain = vec![start];
+                colour[start] = Some(true);
+                let mut i = 0;
+                while i < chain.len() {
+                    let cell = chain[i];
+                    for &next in &links[cell] {
+                        if colour[next].is_none() {
+                            colour[next] = Some(!colour[cell].unwrap());
+                            chain.push(next);
+                        }
+                    }
+                    i += 1;
+                }
+
+                let (on, off): (Vec<usize>, Vec<usize>) =
+                    chain.iter().partition(|&&cell| colour[cell] == Some(true));
+
+                // Colour wrap
+                for group in [&on, &off] {
+                    if combinations(group, 2)
+                        .iter()
+                        .any(|pair| Cell::at(pair[0]).sees(Cell::at(pair[1])))
+                    {
+                        let cells = group.iter().copied();
+                        let detail = format!("(colour wrap on the chain {})", cell_names(&chain));
+                        if let Some(deduction) =
+                            self.eliminate(Technique::SimpleColouring, digit, cells, detail)
+                        {
+                            return Some(deduction);
+                        }
+                    }
+                }
+
+                // Colour trap
+                let others = (0..81).filter(|cell| {
+                    !chain.contains(cell)
+                        && on.iter().any(|&c| Cell::at(*cell).sees(Cell::at(c)))
+                        && off.iter().any(|&c| Cell::at(*cell).sees(Cell::at(c)))
+                });
+                let detail = format!("(colour trap on the chain {})", cell_names(&chain));
+                if let Some(deduction) =
+                    self.eliminate(Technique::SimpleColouring, digit, others, detail)
+                {
+                    return Some(deduction);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{native, parse::parse_line};
+
+    /// Index of the cell at 1-based `row` and `col`.
+    fn at(row: usize, col: usize) -> usize {
+        (row - 1) * 9 + col - 1
+    }
+
+    /// An empty grid whose only pencil marks are `marks`, given as 1-based
+    /// row, column and candidates.
+    fn marked(marks: &[(usize, usize, &[u8])]) -> Board {
+        let mut grid = Board {
+            cells: [0; 81],
+            candidates: [0; 81],
+        };
+        for &(row, col, digits) in marks {
+            grid.candidates[at(row, col)] = digits.iter().fold(0, |mask, &d| mask | bit(d));
+        }
+        grid
+    }
+
+    #[test]
+    fn x_wing_clears_its_columns() {
+        // 5 is only in columns 2 and 8 of rows 1 and 5
+        let grid = marked(&[
+            (1, 2, &[5, 6]),
+            (1, 8, &[5, 6]),
+            (5, 2, &[5, 7]),
+            (5, 8, &[5, 7]),
+            (3, 2, &[4, 5]),
+            (8, 8, &[2, 5]),
+        ]);
+
+        let deduction = grid.fish(2, Technique::XWing).unwrap();
+        assert_eq!(deduction.technique, Technique::XWing);
+        assert_eq!(deduction.eliminations, vec![(at(3, 2), 5), (at(8, 8), 5)]);
+    }
+
+    #[test]
+    fn swordfish_clears_its_columns() {
+        // 3 is only in columns 1, 5 and 9 of rows 1, 4 and 8
+        let grid = marked(&[
+            (1, 1, &[3, 4]),
+            (1, 5, &[3, 6]),
+            (4, 5, &[3, 7]),
+            (4, 9, &[3, 8]),
+            (8, 1, &[1, 3]),
+            (8, 9, &[2, 3]),
+            (6, 5, &[3, 9]),
+        ]);
+
+        assert!(grid.fish(2, Technique::XWing).is_none());
+        let deduction = grid.fish(3, Technique::Swordfish).unwrap();
+        assert_eq!(deduction.eliminations, vec![(at(6, 5), 3)]);
+    }
+
+    #[test]
+    fn xy_wing_clears_cells_seeing_both_pincers() {
+        let grid = marked(&[
+            (1, 1, &[1, 2]),
+            (1, 5, &[1, 3]),
+            (5, 1, &[2, 3]),
+            (5, 5, &[3, 4]),
+        ]);
+
+        let deduction = grid.xy_wing().unwrap();
+        assert_eq!(deduction.technique, Technique::XyWing);
+        assert_eq!(deduction.eliminations, vec![(at(5, 5), 3)]);
+    }
+
+    #[test]
+    fn xyz_wing_clears_cells_seeing_the_pivot_and_pincers() {
+        let grid = marked(&[
+            (1, 1, &[1, 2, 3]),
+            (1, 2, &[1, 3]),
+            (1, 7, &[2, 3]),
+            (1, 3, &[3, 5]),
+            // Sees a pincer but not the pivot
+            (2, 7, &[3, 6]),
+        ]);
+
+        assert!(grid.xy_wing().is_none());
+        let deduction = grid.xyz_wing().unwrap();
+        assert_eq!(deduction.technique, Technique::XyzWing);
+        assert_eq!(deduction.eliminations, vec![(at(1, 3), 3)]);
+    }
+
+    #[test]
+    fn simple_colouring_traps_a_cell_seeing_both_colours() {
+        // The chain r1c1 - r1c5 - r5c5 - r5c2 on 7, and r2c2 seeing its two
+        // ends through box 1 and column 2
+        let grid = marked(&[
+            (1, 1, &[7, 8]),
+            (1, 5, &[7, 8]),
+            (5, 5, &[7, 8]),
+            (5, 2, &[7, 8]),
+            (2, 2, &[6, 7]),
+            (3, 3, &[6, 7]),
+            (8, 2, &[6, 7]),
+        ]);
+
+        let deduction = grid.simple_colouring().unwrap();
+        assert_eq!(deduction.technique, Technique::SimpleColouring);
+        assert_eq!(deduction.eliminations, vec![(at(2, 2), 7)]);
+        assert!(deduction.detail.starts_with("(colour trap"));
+    }
+
+    #[test]
+    fn replay_ends_at_the_solution() {
+        let (grid, _) = parse_line(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let explanation = explain(&grid).unwrap();
+        assert_eq!(explanation.result, LogicResult::Solved);
+
+        let steps: Vec<_> = explanation.replay(&grid).collect();
+        assert_eq!(steps.len(), explanation.deductions.len());
+        assert_eq!(steps.last().unwrap().1, native::solve_grid(&grid, 1)[0]);
+    }
+}
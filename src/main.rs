@@ -1,19 +1,17 @@
+mod cli;
+
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
     io::{self, Write},
+    process::ExitCode,
+    time::Duration,
 };
 
-use nom::{
-    IResult, Parser,
-    bytes::complete::take,
-    character::complete::line_ending,
-    combinator::{all_consuming, opt},
-    multi::{count, separated_list1},
-};
-
-use z3::{
-    Solver,
-    ast::{Ast, Int},
+use sudoku_solver::{
+    BACKENDS, SolveMode, Solver,
+    generator::{self, Generator, Symmetry},
+    grid::{Cell, Digit, Grid},
+    logic,
+    rating::Difficulty,
 };
 
 use crossterm::{
@@ -24,21 +22,70 @@ use crossterm::{
     terminal::{self, ClearType},
 };
 
+/// Time the TUI spends looking for a puzzle of the chosen difficulty.
+const GENERATE_BUDGET: Duration = Duration::from_secs(10);
+
+/// State of the editor when it's used to play a puzzle rather than enter one.
+struct Play {
+    givens: Grid,
+    solution: Grid,
+    mistakes: usize,
+}
+
 struct SudokuInput {
-    grid: [[Option<u8>; 9]; 9],
-    cursor_row: usize,
-    cursor_col: usize,
+    grid: Grid,
+    cursor: Cell,
+    solve_mode: SolveMode,
+    backend: usize,
+    explanation_json: bool,
+    symmetry: Symmetry,
+    target: Option<Difficulty>,
+    play: Option<Play>,
+    message: String,
 }
 
 impl SudokuInput {
     fn new() -> Self {
         Self {
-            grid: [[None; 9]; 9],
-            cursor_row: 0,
-            cursor_col: 0,
+            grid: Grid::new(),
+            cursor: Cell::default(),
+            solve_mode: SolveMode::First,
+            backend: 0,
+            explanation_json: false,
+            symmetry: Symmetry::None,
+            target: None,
+            play: None,
+            message: String::new(),
         }
     }
 
+    /// Starts the editor with the cells of `grid` filled in, 0 marking empty
+    /// cells.
+    fn with_grid(grid: &[[u8; 9]; 9]) -> Self {
+        let mut sudoku = Self::new();
+        sudoku.fill(grid);
+        sudoku
+    }
+
+    /// Starts a game of `grid`, whose only solution is `solution`. The givens
+    /// can't be changed and wrong digits are pointed out.
+    fn play(grid: &[[u8; 9]; 9], solution: [[u8; 9]; 9]) -> Self {
+        let mut sudoku = Self::with_grid(grid);
+        sudoku.play = Some(Play {
+            givens: Grid::from_array(grid),
+            solution: Grid::from_array(&solution),
+            mistakes: 0,
+        });
+        sudoku
+    }
+
+    /// Whether a game is being played and the grid matches its solution.
+    fn is_won(&self) -> bool {
+        self.play
+            .as_ref()
+            .is_some_and(|play| play.solution == self.grid)
+    }
+
     fn display(&self) -> io::Result<()> {
         let mut stdout = io::stdout();
 
@@ -51,13 +98,24 @@ impl SudokuInput {
         queue!(
             stdout,
             Print("╔═══════════════════════════╗\r\n"),
-            Print("║   SUDOKU PUZZLE INPUT     ║\r\n"),
+            Print(if self.play.is_some() {
+                "║       SUDOKU  PLAY        ║\r\n"
+            } else {
+                "║   SUDOKU PUZZLE INPUT     ║\r\n"
+            }),
             Print("╚═══════════════════════════╝\r\n\r\n"),
         )?;
 
         queue!(stdout, Print("  A B C   D E F   G H I\r\n"))?;
         queue!(stdout, Print("┌───────┬───────┬───────┐\r\n"))?;
 
+        let conflicting: Vec<Cell> = self
+            .grid
+            .conflicts()
+            .iter()
+            .flat_map(|conflict| conflict.cells)
+            .collect();
+
         for row in 0..9 {
             if row == 3 || row == 6 {
                 queue!(stdout, Print("├───────┼───────┼───────┤\r\n"))?;
@@ -65,13 +123,13 @@ impl SudokuInput {
 
             queue!(stdout, Print("│ "))?;
 
-            for col in 0..9 {
-                if col == 3 || col == 6 {
+            for (cell, digit) in self.grid.row(row) {
+                if cell.col() == 3 || cell.col() == 6 {
                     queue!(stdout, Print("│ "))?;
                 }
 
                 // Highlight cursor position
-                if row == self.cursor_row && col == self.cursor_col {
+                if cell == self.cursor {
                     queue!(
                         stdout,
                         SetBackgroundColor(Color::White),
@@ -79,12 +137,22 @@ impl SudokuInput {
                     )?;
                 }
 
-                match self.grid[row][col] {
+                // Show the player's own digits apart from the givens
+                let entered = self
+                    .play
+                    .as_ref()
+                    .is_some_and(|play| play.givens[cell].is_none());
+
+                match digit {
+                    Some(n) if conflicting.contains(&cell) => {
+                        queue!(stdout, Print(n.to_string().red().bold()))?
+                    }
+                    Some(n) if entered => queue!(stdout, Print(n.to_string().blue().bold()))?,
                     Some(n) => queue!(stdout, Print(n))?,
                     None => queue!(stdout, Print('.'))?,
                 }
 
-                if row == self.cursor_row && col == self.cursor_col {
+                if cell == self.cursor {
                     queue!(stdout, ResetColor)?;
                 }
 
@@ -104,11 +172,50 @@ impl SudokuInput {
             Print("  0 / Space / Backspace: Clear cell\r\n"),
             Print("  Q / Esc: Quit and show result\r\n"),
             Print("  R: Reset grid\r\n"),
+            Print("  H: Hint (fill in the next logical step)\r\n"),
+        )?;
+
+        if self.play.is_none() {
+            queue!(
+                stdout,
+                Print(format!(
+                    "  M: Cycle solve mode [{}]\r\n",
+                    self.solve_mode.label()
+                )),
+                Print(format!(
+                    "  B: Cycle solver backend [{}]\r\n",
+                    self.solver().name()
+                )),
+                Print(format!(
+                    "  J: Explanation format [{}]\r\n",
+                    if self.explanation_json {
+                        "json"
+                    } else {
+                        "text"
+                    }
+                )),
+                Print("  G: Generate a random puzzle\r\n"),
+                Print("  K: Generate with givens only in filled cells\r\n"),
+                Print(format!(
+                    "  Y: Cycle generator symmetry [{}]\r\n",
+                    self.symmetry
+                )),
+                Print(format!(
+                    "  T: Cycle generator difficulty [{}]\r\n",
+                    self.target
+                        .map_or("any".to_string(), |target| target.to_string())
+                )),
+            )?;
+        }
+
+        queue!(
+            stdout,
             Print(format!(
                 "\r\nCursor: Row {}, Col {}\r\n",
-                (b'A' + self.cursor_row as u8) as char,
-                self.cursor_col + 1
+                (b'A' + self.cursor.row() as u8) as char,
+                self.cursor.col() + 1
             )),
+            Print(format!("{}\r\n", self.message)),
         )?;
 
         stdout.flush()?;
@@ -116,37 +223,198 @@ impl SudokuInput {
     }
 
     fn move_cursor(&mut self, dr: i32, dc: i32) {
-        let new_row = (self.cursor_row as i32 + dr).rem_euclid(9) as usize;
-        let new_col = (self.cursor_col as i32 + dc).rem_euclid(9) as usize;
-        self.cursor_row = new_row;
-        self.cursor_col = new_col;
+        let new_row = (self.cursor.row() as i32 + dr).rem_euclid(9) as usize;
+        let new_col = (self.cursor.col() as i32 + dc).rem_euclid(9) as usize;
+        self.cursor = Cell::new(new_row, new_col).unwrap_or(self.cursor);
     }
 
-    fn set_value(&mut self, val: Option<u8>) {
-        self.grid[self.cursor_row][self.cursor_col] = val;
+    fn set_value(&mut self, val: Option<Digit>) {
+        let cell = self.cursor;
+
+        if let Some(play) = &mut self.play {
+            if play.givens[cell].is_some() {
+                self.message = "That cell is a given".to_string();
+                return;
+            }
+
+            self.message = match val {
+                Some(digit) if Some(digit) != play.solution[cell] => {
+                    play.mistakes += 1;
+                    format!("{cell} isn't {digit}")
+                }
+                _ => String::new(),
+            };
+        }
+
+        self.grid[cell] = val;
+
+        // Point out transcription mistakes as soon as they're made
+        if self.play.is_none()
+            && let Some(conflict) = self
+                .grid
+                .conflicts()
+                .into_iter()
+                .find(|conflict| conflict.cells.contains(&cell))
+        {
+            self.message = format!("Conflict: {conflict}");
+        }
+
+        if self.is_won() {
+            self.message = "Solved! Press Q to finish".to_string();
+        }
     }
 
     fn reset(&mut self) {
-        self.grid = [[None; 9]; 9];
+        self.grid = match &self.play {
+            Some(play) => play.givens,
+            None => Grid::new(),
+        };
     }
 
-    fn to_array(&self) -> [[u8; 9]; 9] {
-        let mut result = [[0u8; 9]; 9];
-        for (row, result_row) in result.iter_mut().enumerate() {
-            for (col, result_cell) in result_row.iter_mut().enumerate() {
-                *result_cell = self.grid[row][col].unwrap_or(0);
+    fn cycle_solve_mode(&mut self) {
+        self.solve_mode = self.solve_mode.next();
+    }
+
+    fn cycle_backend(&mut self) {
+        self.backend = (self.backend + 1) % BACKENDS.len();
+    }
+
+    fn solver(&self) -> &'static dyn Solver {
+        BACKENDS[self.backend]
+    }
+
+    fn toggle_explanation_json(&mut self) {
+        self.explanation_json = !self.explanation_json;
+    }
+
+    /// Fills in the cells solved by the next logical step that places a
+    /// digit, describing the step in the message line. In play the step is
+    /// found from the givens and the correct entries only, so mistakes can't
+    /// lead it astray, and if logic finds none a cell is taken from the
+    /// solution instead.
+    fn hint(&mut self) {
+        let grid = match &self.play {
+            Some(play) => {
+                let mut known = play.givens;
+                for (cell, digit) in self.grid.filled() {
+                    if play.solution[cell] == Some(digit) {
+                        known[cell] = Some(digit);
+                    }
+                }
+                known
+            }
+            None => self.grid,
+        };
+
+        let Some(explanation) = logic::explain(&grid.to_array()) else {
+            self.message = match self.grid.conflicts().first() {
+                Some(conflict) => format!("The givens conflict: {conflict}"),
+                None => "The givens conflict".to_string(),
+            };
+            return;
+        };
+
+        let step = explanation
+            .deductions
+            .iter()
+            .find(|deduction| !deduction.placements.is_empty());
+
+        if let Some(deduction) = step {
+            for &(index, digit) in &deduction.placements {
+                if let Some(cell) = Cell::from_index(index) {
+                    self.grid[cell] = Digit::new(digit);
+                }
             }
+            self.message = format!("Hint: {deduction}");
+        } else if let Some(play) = &self.play {
+            // Prefer the cell under the cursor, then the first one that's
+            // empty or wrong
+            let Some(cell) = std::iter::once(self.cursor)
+                .chain(Cell::all())
+                .find(|&cell| self.grid[cell] != play.solution[cell])
+            else {
+                return;
+            };
+            self.grid[cell] = play.solution[cell];
+            if let Some(digit) = play.solution[cell] {
+                self.message = format!("Hint: {cell} = {digit}");
+            }
+        } else {
+            self.message = "No logical step found".to_string();
+            return;
+        }
+
+        if self.is_won() {
+            self.message.push_str(". Solved! Press Q to finish");
         }
-        result
+    }
+
+    fn cycle_symmetry(&mut self) {
+        self.symmetry = self.symmetry.next();
+    }
+
+    fn cycle_target(&mut self) {
+        self.target = match self.target {
+            None => Some(Difficulty::ALL[0]),
+            Some(target) => Difficulty::ALL
+                .iter()
+                .position(|&d| d == target)
+                .and_then(|index| Difficulty::ALL.get(index + 1))
+                .copied(),
+        };
+    }
+
+    /// Replaces the grid with a freshly generated puzzle, showing the seed so
+    /// it can be reproduced. With `use_mask`, givens are only placed in the
+    /// cells that are currently filled.
+    fn generate(&mut self, use_mask: bool) {
+        let seed = generator::clock_seed();
+
+        let mut generator = Generator::new(seed).symmetry(self.symmetry);
+        if use_mask {
+            generator = generator.mask(self.to_array().map(|row| row.map(|value| value != 0)));
+        }
+
+        let (grid, description) = if let Some(target) = self.target {
+            let targeted = generator.generate_rated(target..=target, GENERATE_BUDGET);
+            let Some((grid, rating)) = targeted.found else {
+                self.message = format!(
+                    "No {target} puzzle found in {} attempts ({:.1?})",
+                    targeted.attempts, targeted.elapsed
+                );
+                return;
+            };
+            (grid, format!("{rating}, {} attempts", targeted.attempts))
+        } else {
+            let Some(grid) = generator.generate() else {
+                self.message = "No unique puzzle fits the filled cells".to_string();
+                return;
+            };
+            (grid, format!("{} symmetry", self.symmetry))
+        };
+
+        self.fill(&grid);
+        self.message = format!("Generated puzzle with seed {seed} ({description})");
+    }
+
+    /// Replaces the grid with `grid`, 0 marking empty cells.
+    fn fill(&mut self, grid: &[[u8; 9]; 9]) {
+        self.grid = Grid::from_array(grid);
+    }
+
+    fn to_array(&self) -> [[u8; 9]; 9] {
+        self.grid.to_array()
     }
 }
 
-fn run() -> io::Result<()> {
+/// Runs the interactive editor on `sudoku` until the user quits, returning
+/// its final state.
+fn run(mut sudoku: SudokuInput) -> io::Result<SudokuInput> {
     let mut stdout = io::stdout();
     terminal::enable_raw_mode()?;
     execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
 
-    let mut sudoku = SudokuInput::new();
+    let editing = sudoku.play.is_none();
     let mut quit = false;
 
     while !quit {
@@ -169,21 +437,38 @@ fn run() -> io::Result<()> {
                 KeyCode::Right | KeyCode::Char('d') | KeyCode::Char('D') => {
                     sudoku.move_cursor(0, 1);
                 }
-                KeyCode::Char('1') => sudoku.set_value(Some(1)),
-                KeyCode::Char('2') => sudoku.set_value(Some(2)),
-                KeyCode::Char('3') => sudoku.set_value(Some(3)),
-                KeyCode::Char('4') => sudoku.set_value(Some(4)),
-                KeyCode::Char('5') => sudoku.set_value(Some(5)),
-                KeyCode::Char('6') => sudoku.set_value(Some(6)),
-                KeyCode::Char('7') => sudoku.set_value(Some(7)),
-                KeyCode::Char('8') => sudoku.set_value(Some(8)),
-                KeyCode::Char('9') => sudoku.set_value(Some(9)),
+                KeyCode::Char(ch @ '1'..='9') => sudoku.set_value(Digit::new(ch as u8 - b'0')),
                 KeyCode::Char('0') | KeyCode::Char(' ') | KeyCode::Backspace | KeyCode::Delete => {
                     sudoku.set_value(None);
                 }
                 KeyCode::Char('r') | KeyCode::Char('R') => {
                     sudoku.reset();
                 }
+                KeyCode::Char('h') | KeyCode::Char('H') => {
+                    sudoku.hint();
+                }
+                // The rest only make sense while entering a puzzle
+                KeyCode::Char('m') | KeyCode::Char('M') if editing => {
+                    sudoku.cycle_solve_mode();
+                }
+                KeyCode::Char('b') | KeyCode::Char('B') if editing => {
+                    sudoku.cycle_backend();
+                }
+                KeyCode::Char('j') | KeyCode::Char('J') if editing => {
+                    sudoku.toggle_explanation_json();
+                }
+                KeyCode::Char('g') | KeyCode::Char('G') if editing => {
+                    sudoku.generate(false);
+                }
+                KeyCode::Char('k') | KeyCode::Char('K') if editing => {
+                    sudoku.generate(true);
+                }
+                KeyCode::Char('y') | KeyCode::Char('Y') if editing => {
+                    sudoku.cycle_symmetry();
+                }
+                KeyCode::Char('t') | KeyCode::Char('T') if editing => {
+                    sudoku.cycle_target();
+                }
                 _ => {}
             }
         }
@@ -193,424 +478,9 @@ fn run() -> io::Result<()> {
     execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
     terminal::disable_raw_mode()?;
 
-    // // Show final grid
-    // println!("\nFinal Sudoku Grid (0 = empty):");
-    // let grid = sudoku.to_array();
-    // for row in grid.iter() {
-    //     println!("{:?}", row);
-    // }
-
-    let mut puzzle = Puzzle::from_array(&sudoku.to_array());
-
-    println!("{puzzle}");
-
-    puzzle.solve();
-
-    Ok(())
-}
-
-fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-    }
-}
-
-// fn main() {
-//     let mut input = String::new();
-
-//     io::stdin()
-//         .read_to_string(&mut input)
-//         .expect("Failed to read input");
-
-//     let (_, mut puzzle) = all_consuming(parse)
-//         .parse(input.as_str())
-//         .expect("should parse");
-
-//     println!("{puzzle}");
-
-//     puzzle.solve();
-// }
-
-fn _parse(input: &str) -> IResult<&str, Puzzle> {
-    let (input, (rows, _)) = all_consuming((
-        separated_list1(line_ending, count(take(1usize), 9)),
-        opt(line_ending),
-    ))
-    .parse(input)?;
-
-    assert!(rows.len() == 9);
-
-    rows.iter().for_each(|row| {
-        assert!(row.len() == 9);
-        row.iter().for_each(|s| assert!(s.len() == 1));
-    });
-
-    let data: BTreeMap<String, Option<u8>> = rows
-        .iter()
-        .enumerate()
-        .flat_map(|(j, row)| {
-            row.iter().enumerate().map(move |(i, s)| {
-                (
-                    format!(
-                        "{}{j}",
-                        match i {
-                            0 => 'a',
-                            1 => 'b',
-                            2 => 'c',
-                            3 => 'd',
-                            4 => 'e',
-                            5 => 'f',
-                            6 => 'g',
-                            7 => 'h',
-                            8 => 'i',
-                            _ => {
-                                panic!("more than 9 columns")
-                            }
-                        }
-                    ),
-                    {
-                        let ch = s.chars().next().unwrap();
-
-                        match ch {
-                            '1'..='9' => Some(ch.to_digit(10).unwrap() as u8),
-                            _ => None,
-                        }
-                    },
-                )
-            })
-        })
-        .collect();
-
-    Ok((
-        input,
-        Puzzle {
-            data: data.clone(),
-            initial_cells: data.keys().cloned().collect(),
-        },
-    ))
+    Ok(sudoku)
 }
 
-#[derive(Debug)]
-struct Puzzle {
-    data: BTreeMap<String, Option<u8>>,
-    initial_cells: HashSet<String>,
-}
-
-impl std::fmt::Display for Puzzle {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "+---+---+---+---+---+---+---+---+---+")?;
-        for row in 0..9 {
-            write!(f, "|")?;
-            for col in 0..9 {
-                let key = format!(
-                    "{}{}",
-                    match col {
-                        0 => 'a',
-                        1 => 'b',
-                        2 => 'c',
-                        3 => 'd',
-                        4 => 'e',
-                        5 => 'f',
-                        6 => 'g',
-                        7 => 'h',
-                        8 => 'i',
-                        _ => panic!("more than 9 columns"),
-                    },
-                    row
-                );
-                write!(
-                    f,
-                    " {} |",
-                    if let Some(v) = self.data.get(&key).unwrap() {
-                        let num_string = String::from((v + b'0') as char);
-
-                        if self.initial_cells.contains(&key) {
-                            num_string.stylize()
-                        } else {
-                            num_string.blue().bold()
-                        }
-                    } else {
-                        " ".to_string().stylize()
-                    }
-                )?;
-            }
-            writeln!(f)?;
-            writeln!(f, "+---+---+---+---+---+---+---+---+---+")?;
-        }
-
-        Ok(())
-    }
-}
-
-impl Puzzle {
-    fn from_array(data: &[[u8; 9]; 9]) -> Self {
-        let initial_cells = data
-            .iter()
-            .enumerate()
-            .flat_map(|(j, row)| {
-                row.iter().enumerate().filter_map(move |(i, value)| {
-                    if matches!(value, 1..=9) {
-                        Some(format!(
-                            "{}{}",
-                            match i {
-                                0 => 'a',
-                                1 => 'b',
-                                2 => 'c',
-                                3 => 'd',
-                                4 => 'e',
-                                5 => 'f',
-                                6 => 'g',
-                                7 => 'h',
-                                8 => 'i',
-                                _ => panic!("more than 9 columns"),
-                            },
-                            (b'0' + j as u8) as char,
-                        ))
-                    } else {
-                        None
-                    }
-                })
-            })
-            .collect();
-
-        Self {
-            data: data
-                .iter()
-                .enumerate()
-                .flat_map(|(j, row)| {
-                    row.iter().enumerate().map(move |(i, value)| {
-                        let key = format!(
-                            "{}{}",
-                            match i {
-                                0 => 'a',
-                                1 => 'b',
-                                2 => 'c',
-                                3 => 'd',
-                                4 => 'e',
-                                5 => 'f',
-                                6 => 'g',
-                                7 => 'h',
-                                8 => 'i',
-                                _ => panic!("more than 9 columns"),
-                            },
-                            (b'0' + j as u8) as char,
-                        );
-
-                        (
-                            key,
-                            match value {
-                                1..=9 => Some(*value),
-                                _ => None,
-                            },
-                        )
-                    })
-                })
-                .collect(),
-            initial_cells,
-        }
-    }
-
-    fn solve(&mut self) {
-        let solver = Solver::new();
-
-        let mut int_vars = HashMap::new();
-
-        int_vars.insert("a0", Int::fresh_const("a0"));
-        int_vars.insert("a1", Int::fresh_const("a1"));
-        int_vars.insert("a2", Int::fresh_const("a2"));
-        int_vars.insert("a3", Int::fresh_const("a3"));
-        int_vars.insert("a4", Int::fresh_const("a4"));
-        int_vars.insert("a5", Int::fresh_const("a5"));
-        int_vars.insert("a6", Int::fresh_const("a6"));
-        int_vars.insert("a7", Int::fresh_const("a7"));
-        int_vars.insert("a8", Int::fresh_const("a8"));
-        int_vars.insert("b0", Int::fresh_const("b0"));
-        int_vars.insert("b1", Int::fresh_const("b1"));
-        int_vars.insert("b2", Int::fresh_const("b2"));
-        int_vars.insert("b3", Int::fresh_const("b3"));
-        int_vars.insert("b4", Int::fresh_const("b4"));
-        int_vars.insert("b5", Int::fresh_const("b5"));
-        int_vars.insert("b6", Int::fresh_const("b6"));
-        int_vars.insert("b7", Int::fresh_const("b7"));
-        int_vars.insert("b8", Int::fresh_const("b8"));
-        int_vars.insert("c0", Int::fresh_const("c0"));
-        int_vars.insert("c1", Int::fresh_const("c1"));
-        int_vars.insert("c2", Int::fresh_const("c2"));
-        int_vars.insert("c3", Int::fresh_const("c3"));
-        int_vars.insert("c4", Int::fresh_const("c4"));
-        int_vars.insert("c5", Int::fresh_const("c5"));
-        int_vars.insert("c6", Int::fresh_const("c6"));
-        int_vars.insert("c7", Int::fresh_const("c7"));
-        int_vars.insert("c8", Int::fresh_const("c8"));
-        int_vars.insert("d0", Int::fresh_const("d0"));
-        int_vars.insert("d1", Int::fresh_const("d1"));
-        int_vars.insert("d2", Int::fresh_const("d2"));
-        int_vars.insert("d3", Int::fresh_const("d3"));
-        int_vars.insert("d4", Int::fresh_const("d4"));
-        int_vars.insert("d5", Int::fresh_const("d5"));
-        int_vars.insert("d6", Int::fresh_const("d6"));
-        int_vars.insert("d7", Int::fresh_const("d7"));
-        int_vars.insert("d8", Int::fresh_const("d8"));
-        int_vars.insert("e0", Int::fresh_const("e0"));
-        int_vars.insert("e1", Int::fresh_const("e1"));
-        int_vars.insert("e2", Int::fresh_const("e2"));
-        int_vars.insert("e3", Int::fresh_const("e3"));
-        int_vars.insert("e4", Int::fresh_const("e4"));
-        int_vars.insert("e5", Int::fresh_const("e5"));
-        int_vars.insert("e6", Int::fresh_const("e6"));
-        int_vars.insert("e7", Int::fresh_const("e7"));
-        int_vars.insert("e8", Int::fresh_const("e8"));
-        int_vars.insert("f0", Int::fresh_const("f0"));
-        int_vars.insert("f1", Int::fresh_const("f1"));
-        int_vars.insert("f2", Int::fresh_const("f2"));
-        int_vars.insert("f3", Int::fresh_const("f3"));
-        int_vars.insert("f4", Int::fresh_const("f4"));
-        int_vars.insert("f5", Int::fresh_const("f5"));
-        int_vars.insert("f6", Int::fresh_const("f6"));
-        int_vars.insert("f7", Int::fresh_const("f7"));
-        int_vars.insert("f8", Int::fresh_const("f8"));
-        int_vars.insert("g0", Int::fresh_const("g0"));
-        int_vars.insert("g1", Int::fresh_const("g1"));
-        int_vars.insert("g2", Int::fresh_const("g2"));
-        int_vars.insert("g3", Int::fresh_const("g3"));
-        int_vars.insert("g4", Int::fresh_const("g4"));
-        int_vars.insert("g5", Int::fresh_const("g5"));
-        int_vars.insert("g6", Int::fresh_const("g6"));
-        int_vars.insert("g7", Int::fresh_const("g7"));
-        int_vars.insert("g8", Int::fresh_const("g8"));
-        int_vars.insert("h0", Int::fresh_const("h0"));
-        int_vars.insert("h1", Int::fresh_const("h1"));
-        int_vars.insert("h2", Int::fresh_const("h2"));
-        int_vars.insert("h3", Int::fresh_const("h3"));
-        int_vars.insert("h4", Int::fresh_const("h4"));
-        int_vars.insert("h5", Int::fresh_const("h5"));
-        int_vars.insert("h6", Int::fresh_const("h6"));
-        int_vars.insert("h7", Int::fresh_const("h7"));
-        int_vars.insert("h8", Int::fresh_const("h8"));
-        int_vars.insert("i0", Int::fresh_const("i0"));
-        int_vars.insert("i1", Int::fresh_const("i1"));
-        int_vars.insert("i2", Int::fresh_const("i2"));
-        int_vars.insert("i3", Int::fresh_const("i3"));
-        int_vars.insert("i4", Int::fresh_const("i4"));
-        int_vars.insert("i5", Int::fresh_const("i5"));
-        int_vars.insert("i6", Int::fresh_const("i6"));
-        int_vars.insert("i7", Int::fresh_const("i7"));
-        int_vars.insert("i8", Int::fresh_const("i8"));
-
-        // Assert that all integers are in the range 1..=9
-        for int_var in int_vars.values() {
-            solver.assert(int_var.ge(Int::from_u64(1)));
-            solver.assert(int_var.le(Int::from_u64(9)));
-        }
-
-        // Assert that all rows have distinct values
-        for row in 0..9 {
-            let mut row_vars = Vec::new();
-            for col in 0..9 {
-                let key = format!(
-                    "{}{}",
-                    match col {
-                        0 => 'a',
-                        1 => 'b',
-                        2 => 'c',
-                        3 => 'd',
-                        4 => 'e',
-                        5 => 'f',
-                        6 => 'g',
-                        7 => 'h',
-                        8 => 'i',
-                        _ => panic!("more than 9 columns"),
-                    },
-                    row
-                );
-                row_vars.push(int_vars.get(&key.as_str()).unwrap().clone());
-            }
-            solver.assert(Int::distinct(&row_vars));
-        }
-
-        // Assert that all columns have distinct values
-        for col in 0..9 {
-            let mut col_vars = Vec::new();
-            for row in 0..9 {
-                let key = format!(
-                    "{}{}",
-                    match col {
-                        0 => 'a',
-                        1 => 'b',
-                        2 => 'c',
-                        3 => 'd',
-                        4 => 'e',
-                        5 => 'f',
-                        6 => 'g',
-                        7 => 'h',
-                        8 => 'i',
-                        _ => panic!("more than 9 columns"),
-                    },
-                    row
-                );
-                col_vars.push(int_vars.get(&key.as_str()).unwrap().clone());
-            }
-            solver.assert(Int::distinct(&col_vars));
-        }
-
-        // Assert that all 3x3 boxes have distinct values
-        for box_row in 0..3 {
-            for box_col in 0..3 {
-                let mut box_vars = Vec::new();
-                for row in 0..3 {
-                    for col in 0..3 {
-                        let key = format!(
-                            "{}{}",
-                            match box_col * 3 + col {
-                                0 => 'a',
-                                1 => 'b',
-                                2 => 'c',
-                                3 => 'd',
-                                4 => 'e',
-                                5 => 'f',
-                                6 => 'g',
-                                7 => 'h',
-                                8 => 'i',
-                                _ => panic!("more than 9 columns"),
-                            },
-                            box_row * 3 + row
-                        );
-                        box_vars.push(int_vars.get(&key.as_str()).unwrap().clone());
-                    }
-                }
-                solver.assert(Int::distinct(&box_vars));
-            }
-        }
-
-        // Assign values to the integers that are known from the initial puzzle data
-
-        for (key, value) in &self.data {
-            if let Some(v) = value
-                && let Some(int_var) = int_vars.get(key.as_str())
-            {
-                solver.assert(int_var.eq(Int::from_u64(*v as u64)));
-            }
-        }
-
-        match solver.check() {
-            z3::SatResult::Sat => {
-                let model = solver.get_model().unwrap();
-
-                for (key, int_var) in &int_vars {
-                    let value = model.eval(int_var, true).unwrap().as_i64().unwrap() as u8;
-
-                    self.data
-                        .entry(key.to_string())
-                        .and_modify(|e| *e = Some(value));
-                }
-                println!("{self}");
-            }
-            z3::SatResult::Unsat => {
-                println!("No solution found");
-            }
-            z3::SatResult::Unknown => {
-                println!("Solver returned unknown");
-            }
-        }
-    }
+fn main() -> ExitCode {
+    cli::main()
 }
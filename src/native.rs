@@ -0,0 +1,160 @@
+//! Pure-Rust solver backend.
+//!
+//! Candidates for every cell are kept as a bitmask (bit `d` set means digit
+//! `d` is still possible). After each placement the board is propagated with
+//! naked and hidden singles, and when that stalls the search branches on the
+//! empty cell with the fewest candidates.
+
+use std::time::Instant;
+
+use crate::{
+    Found, Limits, Puzzle, Solver, UnknownReason,
+    grid::{Board, UNITS},
+    solver::Search,
+};
+
+/// Solves puzzles without z3, using constraint propagation and backtracking.
+pub struct NativeSolver;
+
+impl Solver for NativeSolver {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn find_solutions(&self, puzzle: &Puzzle, limit: usize, limits: &Limits) -> Found {
+        let (solutions, unknown) = solve_grid_within(&puzzle.to_array(), limit, limits);
+
+        Found {
+            solutions: solutions
+                .iter()
+                .map(|grid| puzzle.with_values(grid))
+                .collect(),
+            unknown,
+            explanation: None,
+        }
+    }
+}
+
+/// Returns at most `limit` solutions of `grid`, where 0 marks an empty cell.
+pub fn solve_grid(grid: &[[u8; 9]; 9], limit: usize) -> Vec<[[u8; 9]; 9]> {
+    solve_grid_within(grid, limit, &Limits::new()).0
+}
+
+/// Returns at most `limit` solutions of `grid`, where 0 marks an empty cell,
+/// along with why the search stopped early if `limits` cut it short.
+pub fn solve_grid_within(
+    grid: &[[u8; 9]; 9],
+    limit: usize,
+    limits: &Limits,
+) -> (Vec<[[u8; 9]; 9]>, Option<UnknownReason>) {
+    solve_grid_since(grid, limit, limits, Instant::now())
+}
+
+/// Like [`solve_grid_within`], for a solve that began at `start`, which the
+/// timeout counts from.
+pub(crate) fn solve_grid_since(
+    grid: &[[u8; 9]; 9],
+    limit: usize,
+    limits: &Limits,
+    start: Instant,
+) -> (Vec<[[u8; 9]; 9]>, Option<UnknownReason>) {
+    let mut search = Search::new(limit, limits, start);
+
+    if limit > 0
+        && let Some(board) = Board::from_array(grid)
+    {
+        board.search(&mut search);
+    }
+
+    (search.solutions, search.unknown)
+}
+
+impl Board {
+    /// Applies naked and hidden singles until neither makes progress.
+    /// Returns `false` if the board turns out to be contradictory.
+    fn propagate(&mut self) -> bool {
+        loop {
+            let mut progress = false;
+
+            // Naked singles: a cell with only one candidate left
+            for cell in 0..81 {
+                if self.cells[cell] != 0 {
+                    continue;
+                }
+
+                let candidates = self.candidates[cell];
+                if candidates == 0 {
+                    return false;
+                }
+                if candidates.is_power_of_two() {
+                    if !self.place(cell, candidates.trailing_zeros() as u8) {
+                        return false;
+                    }
+                    progress = true;
+                }
+            }
+
+            // Hidden singles: a digit with only one possible cell in a unit
+            for unit in &UNITS {
+                for digit in 1..=9 {
+                    let bit = 1 << digit;
+
+                    if unit.iter().any(|&cell| self.cells[cell] == digit) {
+                        continue;
+                    }
+
+                    let mut places = unit
+                        .iter()
+                        .filter(|&&cell| self.candidates[cell] & bit != 0);
+
+                    match (places.next(), places.next()) {
+                        (None, _) => return false,
+                        (Some(&cell), None) => {
+                            if !self.place(cell, digit) {
+                                return false;
+                            }
+                            progress = true;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            if !progress {
+                return true;
+            }
+        }
+    }
+
+    /// Collects solutions into `search` until it has enough of them or its
+    /// limits are exceeded.
+    fn search(mut self, search: &mut Search<[[u8; 9]; 9]>) {
+        if search.is_done() || !self.propagate() {
+            return;
+        }
+
+        // Branch on the empty cell with the fewest candidates
+        let Some(cell) = (0..81)
+            .filter(|&cell| self.cells[cell] == 0)
+            .min_by_key(|&cell| self.candidates[cell].count_ones())
+        else {
+            search.solutions.push(self.to_array());
+            return;
+        };
+
+        for digit in 1..=9 {
+            if self.candidates[cell] & (1 << digit) == 0 {
+                continue;
+            }
+
+            let mut branch = self.clone();
+            if branch.place(cell, digit) {
+                branch.search(search);
+            }
+
+            if search.is_done() {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! Reading puzzles from text.
+//!
+//! Two formats are understood, both using the digits 1 to 9 for givens and
+//! `.`, `0` or `_` for empty cells, and ignoring trailing blank lines:
+//!
+//! - [`Format::Grid`]: 9 lines of 9 characters each.
+//! - [`Format::Line`]: all 81 cells on one line, row by row, optionally
+//!   followed by whitespace and a comment. Most published puzzle collections
+//!   use this format.
+//!
+//! A file may hold many puzzles: one-line puzzles one per line, and grids
+//! separated by blank lines. Lines starting with `#` are comments.
+
+use std::{
+    error, fmt, fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    character::complete::{line_ending, multispace0, not_line_ending, one_of, space1},
+    combinator::{all_consuming, eof, map, opt},
+    multi::count,
+    sequence::{preceded, terminated},
+};
+
+/// Text layout of a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Grid,
+    Line,
+}
+
+/// Where and why a grid failed to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line of the offending character.
+    pub line: usize,
+    /// 1-based column of the offending character.
+    pub column: usize,
+    pub expected: &'static str,
+    /// The offending character, or `None` at the end of the input.
+    pub found: Option<char>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: expected {}, found ",
+            self.line, self.column, self.expected
+        )?;
+        match self.found {
+            None => write!(f, "end of input"),
+            Some('\r' | '\n') => write!(f, "end of line"),
+            Some(ch) => write!(f, "{ch:?}"),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Failure to read a puzzle from a file or stdin.
+#[derive(Debug)]
+pub enum InputError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Io(e) => write!(f, "I/O error: {e}"),
+            InputError::Parse(e) => write!(f, "invalid puzzle: {e}"),
+        }
+    }
+}
+
+impl error::Error for InputError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            InputError::Io(e) => Some(e),
+            InputError::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for InputError {
+    fn from(e: io::Error) -> Self {
+        InputError::Io(e)
+    }
+}
+
+impl From<ParseError> for InputError {
+    fn from(e: ParseError) -> Self {
+        InputError::Parse(e)
+    }
+}
+
+/// A puzzle read from a file that may hold several.
+#[derive(Debug, Clone)]
+pub struct Parsed {
+    /// Cell values, with 0 marking empty cells.
+    pub grid: [[u8; 9]; 9],
+    pub format: Format,
+    /// Comment following a one-line puzzle.
+    pub comment: Option<String>,
+    /// 1-based line the puzzle starts on.
+    pub line: usize,
+}
+
+/// Reads every puzzle in the file at `path`, or on stdin if `path` is `None`.
+pub fn read_puzzles(path: Option<&Path>) -> Result<Vec<Parsed>, InputError> {
+    let input = match path {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            input
+        }
+    };
+
+    Ok(parse_puzzles(&input)?)
+}
+
+/// Parses every puzzle in `input`, in either format.
+///
+/// Blank and comment lines split the input into blocks. A block whose first
+/// line is at most 9 characters long is a grid, any other block holds one
+/// one-line puzzle per line.
+pub fn parse_puzzles(input: &str) -> Result<Vec<Parsed>, ParseError> {
+    let mut puzzles = Vec::new();
+    let mut block: Vec<(usize, &str)> = Vec::new();
+
+    let lines = input.lines().enumerate().map(|(i, text)| (i + 1, text));
+    for (number, text) in lines.chain([(0, "")]) {
+        let trimmed = text.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            block.push((number, text));
+            continue;
+        }
+
+        let Some(&(first, first_text)) = block.first() else {
+            continue;
+        };
+
+        if first_text.trim_end().chars().count() <= 9 {
+            let text: Vec<&str> = block.iter().map(|&(_, text)| text).collect();
+            let grid = parse_grid(&text.join("\n")).map_err(|mut e| {
+                // Point at the file's line rather than the block's
+                e.line = block
+                    .get(e.line - 1)
+                    .map_or(first + block.len(), |&(n, _)| n);
+                e
+            })?;
+
+            puzzles.push(Parsed {
+                grid,
+                format: Format::Grid,
+                comment: None,
+                line: first,
+            });
+        } else {
+            for &(number, text) in &block {
+                let (grid, comment) =
+                    parse_line(text).map_err(|e| ParseError { line: number, ..e })?;
+
+                puzzles.push(Parsed {
+                    grid,
+                    format: Format::Line,
+                    comment: comment.map(String::from),
+                    line: number,
+                });
+            }
+        }
+
+        block.clear();
+    }
+
+    if puzzles.is_empty() {
+        return Err(ParseError {
+            line: input.lines().count() + 1,
+            column: 1,
+            expected: "a puzzle",
+            found: None,
+        });
+    }
+
+    Ok(puzzles)
+}
+
+/// Parses a 9-line grid, with 0 marking empty cells in the result.
+pub fn parse_grid(input: &str) -> Result<[[u8; 9]; 9], ParseError> {
+    match all_consuming(terminated(count(row, 9), multispace0)).parse(input) {
+        Ok((_, rows)) => {
+            let mut grid = [[0u8; 9]; 9];
+            for (values, parsed) in grid.iter_mut().zip(rows) {
+                values.copy_from_slice(&parsed);
+            }
+            Ok(grid)
+        }
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+            let mut error = error_at(input, e.input, |line, column| {
+                if line > 9 {
+                    "end of input"
+                } else if column > 9 {
+                    "end of line"
+                } else {
+                    "a digit 1-9 or '.'"
+                }
+            });
+
+            // Input ending right after a full row is missing the next one
+            if error.found.is_none() && error.line < 9 && error.column > 9 {
+                error.line += 1;
+                error.column = 1;
+                error.expected = "a digit 1-9 or '.'";
+            }
+            Err(error)
+        }
+        Err(nom::Err::Incomplete(_)) => unreachable!("complete parsers never ask for more input"),
+    }
+}
+
+/// Parses a one-line puzzle, returning the grid with 0 marking empty cells
+/// and the trailing comment, if any.
+pub fn parse_line(input: &str) -> Result<([[u8; 9]; 9], Option<&str>), ParseError> {
+    let comment = opt(preceded(space1, not_line_ending));
+
+    match all_consuming(terminated((count(cell, 81), comment), multispace0)).parse(input) {
+        Ok((_, (cells, comment))) => {
+            let mut grid = [[0u8; 9]; 9];
+            for (i, value) in cells.into_iter().enumerate() {
+                grid[i / 9][i % 9] = value;
+            }
+            let comment = comment.map(str::trim).filter(|comment| !comment.is_empty());
+            Ok((grid, comment))
+        }
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+            Err(error_at(input, e.input, |line, column| {
+                if line > 1 {
+                    "end of input"
+                } else if column > 81 {
+                    "whitespace before a comment"
+                } else {
+                    "a digit 1-9 or '.'"
+                }
+            }))
+        }
+        Err(nom::Err::Incomplete(_)) => unreachable!("complete parsers never ask for more input"),
+    }
+}
+
+fn row(input: &str) -> IResult<&str, Vec<u8>> {
+    terminated(count(cell, 9), alt((line_ending, eof))).parse(input)
+}
+
+fn cell(input: &str) -> IResult<&str, u8> {
+    map(one_of("123456789._0"), |ch| {
+        ch.to_digit(10).unwrap_or(0) as u8
+    })
+    .parse(input)
+}
+
+/// Describes a failure at `rest`, the unparsed tail of `input`, using
+/// `expected` to say what should have been at that line and column.
+fn error_at(
+    input: &str,
+    rest: &str,
+    expected: impl Fn(usize, usize) -> &'static str,
+) -> ParseError {
+    let consumed = &input[..input.len() - rest.len()];
+    let line = consumed.matches('\n').count() + 1;
+    let column = consumed
+        .rsplit('\n')
+        .next()
+        .map_or(0, |text| text.chars().count())
+        + 1;
+
+    ParseError {
+        line,
+        column,
+        expected: expected(line, column),
+        found: rest.chars().next(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str =
+        "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+    /// The rows of [`LINE`] as a grid, one per line.
+    fn grid() -> Vec<String> {
+        (0..9).map(|row| LINE[row * 9..][..9].to_string()).collect()
+    }
+
+    #[test]
+    fn grid_errors_point_at_the_character() {
+        let mut rows = grid();
+        rows[2].replace_range(4..5, "x");
+
+        let e = parse_grid(&rows.join("\n")).unwrap_err();
+        assert_eq!((e.line, e.column, e.found), (3, 5, Some('x')));
+        assert_eq!(e.expected, "a digit 1-9 or '.'");
+    }
+
+    #[test]
+    fn grid_errors_point_past_a_long_row() {
+        let mut rows = grid();
+        rows[6].push('1');
+
+        let e = parse_grid(&rows.join("\n")).unwrap_err();
+        assert_eq!((e.line, e.column, e.found), (7, 10, Some('1')));
+        assert_eq!(e.expected, "end of line");
+    }
+
+    #[test]
+    fn grid_errors_point_at_a_short_row() {
+        let mut rows = grid();
+        rows[1].pop();
+
+        let e = parse_grid(&rows.join("\n")).unwrap_err();
+        assert_eq!((e.line, e.column, e.found), (2, 9, Some('\n')));
+    }
+
+    #[test]
+    fn line_errors_point_at_the_character() {
+        let mut line = LINE.to_string();
+        line.replace_range(39..40, "?");
+
+        let e = parse_line(&line).unwrap_err();
+        assert_eq!((e.line, e.column, e.found), (1, 40, Some('?')));
+        assert_eq!(e.expected, "a digit 1-9 or '.'");
+    }
+
+    #[test]
+    fn line_errors_point_at_a_comment_without_whitespace() {
+        let e = parse_line(&format!("{LINE}#1")).unwrap_err();
+        assert_eq!((e.line, e.column, e.found), (1, 82, Some('#')));
+        assert_eq!(e.expected, "whitespace before a comment");
+    }
+
+    #[test]
+    fn file_errors_point_at_the_line_in_the_file() {
+        let mut rows = grid();
+        rows[3].replace_range(8..9, "x");
+        let input = format!("# a grid\n\n{}\n", rows.join("\n"));
+
+        let e = parse_puzzles(&input).unwrap_err();
+        assert_eq!((e.line, e.column, e.found), (6, 9, Some('x')));
+
+        let input = format!("# one-line puzzles\n{LINE}\n{}\n", &LINE[..80]);
+
+        let e = parse_puzzles(&input).unwrap_err();
+        assert_eq!((e.line, e.column, e.found), (3, 81, None));
+    }
+
+    #[test]
+    fn file_errors_point_past_a_truncated_grid() {
+        let rows = grid();
+        let input = format!("{}\n\n{}\n", rows.join("\n"), rows[..8].join("\n"));
+
+        let e = parse_puzzles(&input).unwrap_err();
+        assert_eq!((e.line, e.column, e.found), (19, 1, None));
+        assert_eq!(e.expected, "a digit 1-9 or '.'");
+    }
+}
@@ -0,0 +1,543 @@
+//! A puzzle grid and solving it with z3, in any [`Encoding`].
+
+use std::{
+    fmt,
+    sync::{Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use z3::{Context, Model, Params, SatResult, ast::Bool};
+
+use crate::{
+    encoding::{CellVars, Encoding},
+    grid::{Cell, Conflict, Digit, Grid},
+    native,
+    solver::{CancelToken, Limits, SolveError, SolveMode, SolveOutcome, Solver, UnknownReason},
+};
+
+/// How often a running z3 check looks at its [`CancelToken`].
+const CANCEL_POLL: Duration = Duration::from_millis(10);
+
+/// A sudoku grid, remembering which cells were given.
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    grid: Grid,
+    /// The digits the puzzle started with.
+    givens: Grid,
+}
+
+/// Writes a boxed table of the grid without any styling.
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_table(f, |_, digit| digit)
+    }
+}
+
+impl Puzzle {
+    /// The puzzle whose givens are `grid`.
+    pub fn new(grid: Grid) -> Self {
+        Self { grid, givens: grid }
+    }
+
+    /// The puzzle whose givens are `data`, where 0 marks an empty cell.
+    pub fn from_array(data: &[[u8; 9]; 9]) -> Self {
+        Self::new(Grid::from_array(data))
+    }
+
+    /// The current contents of the grid.
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Whether `cell` held a digit when the puzzle started.
+    pub fn is_given(&self, cell: Cell) -> bool {
+        self.givens[cell].is_some()
+    }
+
+    /// Returns the givens, in reading order.
+    pub fn givens(&self) -> Vec<(Cell, Digit)> {
+        self.givens.filled().collect()
+    }
+
+    /// Writes the grid as a boxed table, showing each filled cell as `show`
+    /// renders its digit, e.g. to tell givens from solved cells.
+    pub fn write_table<D: fmt::Display>(
+        &self,
+        out: &mut dyn fmt::Write,
+        show: impl Fn(Cell, Digit) -> D,
+    ) -> fmt::Result {
+        writeln!(out, "+---+---+---+---+---+---+---+---+---+")?;
+        for row in 0..9 {
+            write!(out, "|")?;
+            for (cell, digit) in self.grid.row(row) {
+                match digit {
+                    Some(digit) => write!(out, " {} |", show(cell, digit))?,
+                    None => write!(out, "   |")?,
+                }
+            }
+            writeln!(out)?;
+            writeln!(out, "+---+---+---+---+---+---+---+---+---+")?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the grid as 9 lines of 9 characters, with `.` for empty cells.
+    pub fn to_grid(&self) -> String {
+        self.to_line()
+            .as_bytes()
+            .chunks(9)
+            .map(|row| format!("{}\n", String::from_utf8_lossy(row)))
+            .collect()
+    }
+
+    /// Returns the grid in the one-line format, with `.` for empty cells.
+    pub fn to_line(&self) -> String {
+        self.grid
+            .cells()
+            .map(|(_, digit)| digit.map_or('.', |digit| (b'0' + digit.get()) as char))
+            .collect()
+    }
+
+    /// Returns the current values of the grid, with 0 for empty cells.
+    pub fn to_array(&self) -> [[u8; 9]; 9] {
+        self.grid.to_array()
+    }
+
+    /// Returns a copy of this puzzle with the cells filled in from `values`,
+    /// keeping track of which cells were given.
+    pub fn with_values(&self, values: &[[u8; 9]; 9]) -> Puzzle {
+        let mut puzzle = self.clone();
+        for (cell, digit) in Grid::from_array(values).filled() {
+            puzzle.grid[cell] = Some(digit);
+        }
+        puzzle
+    }
+
+    /// Solves the puzzle with `solver`, looking for as many solutions as
+    /// `mode` needs, and giving up once `limits` are exceeded.
+    pub fn solve(
+        &self,
+        mode: SolveMode,
+        solver: &dyn Solver,
+        limits: &Limits,
+    ) -> Result<SolveOutcome, SolveError> {
+        let start = Instant::now();
+
+        let conflicts = self.conflicts();
+        if !conflicts.is_empty() {
+            return Err(SolveError::ConflictingGivens(conflicts));
+        }
+
+        if let SolveMode::Count(limit) = mode {
+            return Ok(match solver.count_solutions(self, limit, limits) {
+                Ok(count) => SolveOutcome::Counted(count),
+                Err(reason) => SolveOutcome::Unknown(reason),
+            });
+        }
+
+        let found = solver.find_solutions(self, mode.solution_limit(), limits);
+        let mut solutions = found.solutions.into_iter();
+
+        let Some(solution) = solutions.next() else {
+            return Ok(match found.unknown {
+                Some(reason) => SolveOutcome::Unknown(reason),
+                None => SolveOutcome::Unsatisfiable {
+                    contradiction: self.contradiction_since(limits, start),
+                },
+            });
+        };
+
+        if mode == SolveMode::Unique {
+            if let Some(other) = solutions.next() {
+                return Ok(SolveOutcome::NotUnique(solution, other));
+            }
+            if let Some(reason) = found.unknown {
+                return Ok(SolveOutcome::Unknown(reason));
+            }
+        }
+
+        Ok(SolveOutcome::Solved {
+            solution,
+            explanation: found.explanation,
+        })
+    }
+
+    /// Every pair of filled cells that see each other and hold the same
+    /// digit. Such a puzzle has no solution.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        self.grid.conflicts()
+    }
+
+    /// Returns an iterator over at most `limit` distinct solutions of the
+    /// puzzle, found by z3 with the rules in `encoding`, which stops early
+    /// once `limits` are exceeded.
+    pub fn solutions(&self, limit: usize, encoding: Encoding, limits: &Limits) -> Solutions {
+        let (solver, cell_vars) = self.build_solver(encoding);
+
+        Solutions {
+            puzzle: self.clone(),
+            solver,
+            cell_vars,
+            remaining: limit,
+            limits: limits.clone(),
+            start: Instant::now(),
+            unknown: None,
+        }
+    }
+
+    /// Counts the solutions of the puzzle, stopping once `limit` is reached.
+    /// Returns why z3 gave up if it did before finishing.
+    pub fn count_solutions(
+        &self,
+        limit: usize,
+        encoding: Encoding,
+        limits: &Limits,
+    ) -> Result<usize, UnknownReason> {
+        let mut solutions = self.solutions(limit, encoding, limits);
+
+        // Counting doesn't need the grids, so skip building them
+        let mut count = 0;
+        while solutions.next_model().is_some() {
+            count += 1;
+        }
+
+        match solutions.reason_unknown() {
+            Some(reason) => Err(reason),
+            None => Ok(count),
+        }
+    }
+
+    /// Finds a small set of givens that can't all hold together, e.g. for
+    /// an editor to point at the few clues worth re-examining. Returns `None`
+    /// if the puzzle has a solution or `limits` run out before the set is
+    /// found.
+    ///
+    /// The set is minimal rather than minimum: dropping any one of its givens
+    /// leaves a set that can be satisfied, but a smaller, different set may
+    /// exist.
+    pub fn contradiction(&self, limits: &Limits) -> Option<Vec<(Cell, Digit)>> {
+        self.contradiction_since(limits, Instant::now())
+    }
+
+    /// Like [`Puzzle::contradiction`], for a solve that began at `start`,
+    /// which the timeout counts from.
+    pub(crate) fn contradiction_since(
+        &self,
+        limits: &Limits,
+        start: Instant,
+    ) -> Option<Vec<(Cell, Digit)>> {
+        let (solver, cell_vars) = Self::build_rules(Encoding::default());
+
+        // Each given only holds while its literal is assumed, so z3 can say
+        // which of them it needed to reach a contradiction
+        let givens: Vec<(Cell, Digit)> = self.grid.filled().collect();
+        let literals: Vec<Bool> = givens
+            .iter()
+            .map(|&(cell, digit)| {
+                let literal = Bool::new_const(format!("{cell}={digit}"));
+                solver.assert(literal.implies(cell_vars.holds(cell, digit)));
+                literal
+            })
+            .collect();
+
+        if check_within(&solver, &literals, limits, start) != Ok(false) {
+            return None;
+        }
+
+        let mut core: Vec<(Cell, Digit)> = solver
+            .get_unsat_core()
+            .iter()
+            .filter_map(|literal| Some(givens[literals.iter().position(|l| l == literal)?]))
+            .collect();
+        core.sort_unstable();
+
+        // z3's core needn't be minimal, so try dropping each given in turn.
+        // Sparse grids are slow for z3 to satisfy but quick for the native
+        // solver, which does these checks like it does for the generator
+        let mut i = 0;
+        while i < core.len() {
+            let mut rest = core.clone();
+            rest.remove(i);
+
+            let mut grid = Grid::new();
+            for &(cell, digit) in &rest {
+                grid[cell] = Some(digit);
+            }
+
+            match native::solve_grid_since(&grid.to_array(), 1, limits, start) {
+                (_, Some(_)) => return None,
+                (solutions, None) if solutions.is_empty() => core = rest,
+                _ => i += 1,
+            }
+        }
+
+        Some(core)
+    }
+
+    /// Writes the assertions z3 solves this puzzle with as an SMT-LIB2
+    /// script, so it can be replayed with any SMT solver: the rules in
+    /// `encoding` and the filled cells, followed by `(check-sat)` and
+    /// `(get-model)`.
+    pub fn to_smt2(&self, encoding: Encoding) -> String {
+        let (solver, _) = self.build_solver(encoding);
+        format!(
+            "; Sudoku {}, {encoding} encoding\n{}(get-model)\n",
+            self.to_line(),
+            solver.to_smt2()
+        )
+    }
+
+    /// Builds a z3 solver holding the sudoku rules in `encoding` and the
+    /// givens of this puzzle, along with the variables of every cell.
+    fn build_solver(&self, encoding: Encoding) -> (z3::Solver, CellVars) {
+        let (solver, cell_vars) = Self::build_rules(encoding);
+
+        // Fix the cells that are known from the initial puzzle data
+        for (cell, digit) in self.grid.filled() {
+            solver.assert(cell_vars.holds(cell, digit));
+        }
+
+        (solver, cell_vars)
+    }
+
+    /// Builds a z3 solver holding only the sudoku rules in `encoding`, along
+    /// with the variables of every cell.
+    fn build_rules(encoding: Encoding) -> (z3::Solver, CellVars) {
+        let solver = z3::Solver::new();
+        let cell_vars = CellVars::new(encoding);
+        cell_vars.assert_rules(&solver);
+
+        (solver, cell_vars)
+    }
+
+    /// Copies the value of every cell from `model` into the grid.
+    fn apply_model(&mut self, model: &Model, cell_vars: &CellVars) {
+        for cell in Cell::all() {
+            self.grid[cell] = cell_vars.value(model, cell);
+        }
+    }
+}
+
+/// Enumerates the solutions of a puzzle by repeatedly asking z3 for a model
+/// and then blocking that exact assignment.
+///
+/// Iteration stops after `limit` solutions, once the solver reports `Unsat`,
+/// or when it gives up with `Unknown`; `reason_unknown` tells these apart.
+pub struct Solutions {
+    puzzle: Puzzle,
+    solver: z3::Solver,
+    cell_vars: CellVars,
+    remaining: usize,
+    limits: Limits,
+    /// When the iterator was created, which the timeout counts from.
+    start: Instant,
+    unknown: Option<UnknownReason>,
+}
+
+impl Solutions {
+    /// Why z3 gave up, if its most recent check returned `Unknown`.
+    pub fn reason_unknown(&self) -> Option<UnknownReason> {
+        self.unknown.clone()
+    }
+
+    fn next_model(&mut self) -> Option<Model> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        match check_within(&self.solver, &[], &self.limits, self.start) {
+            Ok(true) => {}
+            Ok(false) => {
+                self.remaining = 0;
+                return None;
+            }
+            Err(reason) => {
+                self.remaining = 0;
+                self.unknown = Some(reason);
+                return None;
+            }
+        }
+
+        self.remaining -= 1;
+
+        let model = self.solver.get_model().unwrap();
+
+        // At least one cell has to differ from the solution we just found
+        let blocking: Vec<Bool> = Cell::all()
+            .filter_map(|cell| {
+                let digit = self.cell_vars.value(&model, cell)?;
+                Some(self.cell_vars.holds(cell, digit).not())
+            })
+            .collect();
+
+        self.solver.assert(Bool::or(&blocking));
+
+        Some(model)
+    }
+}
+
+impl Iterator for Solutions {
+    type Item = Puzzle;
+
+    fn next(&mut self) -> Option<Puzzle> {
+        let model = self.next_model()?;
+
+        let mut solution = self.puzzle.clone();
+        solution.apply_model(&model, &self.cell_vars);
+
+        Some(solution)
+    }
+}
+
+/// Checks whether the assertions of `solver` hold together with
+/// `assumptions`, giving up once `limits` are exceeded, with the timeout
+/// counted from `start`.
+fn check_within(
+    solver: &z3::Solver,
+    assumptions: &[Bool],
+    limits: &Limits,
+    start: Instant,
+) -> Result<bool, UnknownReason> {
+    if limits.is_canceled() {
+        return Err(UnknownReason::Canceled);
+    }
+
+    let mut params = Params::new();
+    if let Some(timeout) = limits.timeout {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return Err(UnknownReason::Timeout);
+        }
+        params.set_u32(
+            "timeout",
+            remaining.as_millis().clamp(1, u32::MAX as u128) as u32,
+        );
+    }
+    if let Some(resource_limit) = limits.resource_limit {
+        params.set_u32("rlimit", resource_limit);
+    }
+    solver.set_params(&params);
+
+    let result = match &limits.cancel {
+        Some(cancel) => interruptible(cancel, || solver.check_assumptions(assumptions)),
+        None => solver.check_assumptions(assumptions),
+    };
+
+    match result {
+        SatResult::Sat => Ok(true),
+        SatResult::Unsat => Ok(false),
+        // z3 reports interruptions and timeouts alike as "canceled", so tell
+        // them apart by which limit was reached
+        SatResult::Unknown if limits.is_canceled() => Err(UnknownReason::Canceled),
+        SatResult::Unknown
+            if limits
+                .timeout
+                .is_some_and(|timeout| start.elapsed() >= timeout) =>
+        {
+            Err(UnknownReason::Timeout)
+        }
+        SatResult::Unknown => {
+            match UnknownReason::from_z3(&solver.get_reason_unknown().unwrap_or_default()) {
+                // z3 often reports an exhausted rlimit as "canceled" too, and
+                // actual cancellation was ruled out above
+                UnknownReason::Canceled if limits.resource_limit.is_some() => {
+                    Err(UnknownReason::ResourceLimit)
+                }
+                reason => Err(reason),
+            }
+        }
+    }
+}
+
+/// Runs `check` on this thread's z3 context, interrupting it if `cancel` is
+/// canceled before it returns.
+fn interruptible<T>(cancel: &CancelToken, check: impl FnOnce() -> T) -> T {
+    let context = Context::thread_local();
+    let handle = context.handle();
+    let running = Mutex::new(true);
+
+    let (result, interrupted) = thread::scope(|scope| {
+        let watcher = scope.spawn(|| {
+            loop {
+                // Only interrupt while the check runs, holding the lock so it
+                // can't finish in between
+                let running = running.lock().unwrap_or_else(PoisonError::into_inner);
+                if !*running {
+                    return false;
+                }
+                if cancel.is_canceled() {
+                    handle.interrupt();
+                    return true;
+                }
+                drop(running);
+
+                thread::park_timeout(CANCEL_POLL);
+            }
+        });
+
+        let result = check();
+        *running.lock().unwrap_or_else(PoisonError::into_inner) = false;
+        watcher.thread().unpark();
+
+        (result, watcher.join().unwrap_or(false))
+    });
+
+    // z3 may have finished just as it was interrupted, in which case the
+    // interrupt would cut short whatever this thread asks of z3 next. Use it
+    // up on a trivial check instead
+    if interrupted {
+        z3::Solver::new().check();
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_line;
+
+    #[test]
+    fn exhausted_resource_limit_is_reported_as_such() {
+        let puzzle = Puzzle::new(Grid::new());
+        let limits = Limits::new().resource_limit(50_000);
+
+        assert_eq!(
+            puzzle.count_solutions(2, Encoding::Int, &limits),
+            Err(UnknownReason::ResourceLimit)
+        );
+    }
+
+    /// Whether some grid holding just `givens` has a solution.
+    fn satisfiable(givens: &[(Cell, Digit)]) -> bool {
+        let mut grid = Grid::new();
+        for &(cell, digit) in givens {
+            grid[cell] = Some(digit);
+        }
+        !native::solve_grid(&grid.to_array(), 1).is_empty()
+    }
+
+    #[test]
+    fn contradiction_is_minimal() {
+        let puzzle = Puzzle::from_array(
+            &parse_line(
+                "82.........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..",
+            )
+            .unwrap()
+            .0,
+        );
+        assert!(puzzle.conflicts().is_empty());
+
+        let core = puzzle.contradiction(&Limits::new()).unwrap();
+        assert!(core.iter().all(|given| puzzle.givens().contains(given)));
+        assert!(!satisfiable(&core));
+
+        for i in 0..core.len() {
+            let mut rest = core.clone();
+            rest.remove(i);
+            assert!(satisfiable(&rest), "{:?} isn't needed", core[i]);
+        }
+    }
+}
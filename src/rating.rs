@@ -0,0 +1,256 @@
+//! Difficulty rating based on the techniques the logical solver needs.
+//!
+//! Like Sudoku Explainer (SE), a puzzle is scored by the hardest step on its
+//! easiest logical path, so scores are roughly comparable with published SE
+//! ratings. How often that hardest technique is needed breaks ties.
+
+use std::{cmp::Ordering, error, fmt};
+
+use serde_json::json;
+
+use crate::{
+    logic::{self, LogicResult, Technique},
+    native,
+};
+
+/// Score given to puzzles the logical solver can't finish. The techniques it
+/// would need are unknown, so this is just above the hardest one it has.
+const STALLED_SCORE: f32 = 7.0;
+
+/// Approximate SE rating of a single application of `technique`.
+pub fn technique_score(technique: Technique) -> f32 {
+    match technique {
+        Technique::HiddenSingle => 1.5,
+        Technique::NakedSingle => 2.3,
+        Technique::PointingPair => 2.6,
+        Technique::BoxLineReduction => 2.8,
+        Technique::NakedPair => 3.0,
+        Technique::XWing => 3.2,
+        Technique::HiddenPair => 3.4,
+        Technique::NakedTriple => 3.6,
+        Technique::Swordfish => 3.8,
+        Technique::HiddenTriple => 4.0,
+        Technique::XyWing => 4.2,
+        Technique::XyzWing => 4.4,
+        Technique::NakedQuad => 5.0,
+        Technique::HiddenQuad => 5.4,
+        // SE has no simple colouring; it rates the equivalent X-Chains here
+        Technique::SimpleColouring => 6.6,
+    }
+}
+
+/// Coarse difficulty label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    /// Singles only.
+    Easy,
+    /// Needs pointing pairs or box/line reductions.
+    Medium,
+    /// Needs subsets of up to three cells, X-Wings or Swordfish.
+    Hard,
+    /// Needs wings, quads or colouring.
+    Expert,
+    /// Can't be finished by the logical solver.
+    Diabolical,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Expert,
+        Difficulty::Diabolical,
+    ];
+
+    fn from_score(score: f32) -> Self {
+        match score {
+            s if s <= 2.3 => Difficulty::Easy,
+            s if s <= 2.8 => Difficulty::Medium,
+            s if s <= 4.0 => Difficulty::Hard,
+            s if s < STALLED_SCORE => Difficulty::Expert,
+            _ => Difficulty::Diabolical,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+            Difficulty::Expert => "Expert",
+            Difficulty::Diabolical => "Diabolical",
+        })
+    }
+}
+
+/// How hard a puzzle is to solve by hand. Ratings order from easiest to
+/// hardest, so a pack of puzzles can be sorted by them directly.
+#[derive(Debug, Clone)]
+pub struct Rating {
+    pub difficulty: Difficulty,
+    /// SE-style score of the hardest step.
+    pub score: f32,
+    /// Hardest technique used, or `None` if the solver stalled before using
+    /// any.
+    pub hardest: Option<Technique>,
+    /// Number of times `hardest` was applied.
+    pub hardest_uses: usize,
+    /// Total number of logical steps taken.
+    pub steps: usize,
+    /// Whether the logical solver stalled, i.e. the puzzle needs techniques
+    /// beyond the ones it knows or guessing.
+    pub stalled: bool,
+}
+
+impl Ord for Rating {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then(self.hardest_uses.cmp(&other.hardest_uses))
+            .then(self.steps.cmp(&other.steps))
+    }
+}
+
+impl PartialOrd for Rating {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Rating {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Rating {}
+
+impl Rating {
+    /// Serialises the rating for tools that sort or filter puzzles by it.
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "difficulty": self.difficulty.to_string(),
+            // Rounded as in the text form, rather than with f32 noise
+            "score": (f64::from(self.score) * 10.0).round() / 10.0,
+            "hardest": self.hardest.map(Technique::name),
+            "hardest_uses": self.hardest_uses,
+            "steps": self.steps,
+            "stalled": self.stalled,
+        })
+    }
+}
+
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (SE {:.1}", self.difficulty, self.score)?;
+        if self.stalled {
+            write!(f, ", logic stalls after {} steps", self.steps)?;
+        } else if let Some(hardest) = self.hardest {
+            write!(f, ", hardest: {hardest} x{}", self.hardest_uses)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Why a puzzle can't be rated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RatingError {
+    NoSolution,
+    /// A rating assumes one solution to reason towards.
+    NotUnique,
+}
+
+impl fmt::Display for RatingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RatingError::NoSolution => "no solution",
+            RatingError::NotUnique => "more than one solution",
+        })
+    }
+}
+
+impl error::Error for RatingError {}
+
+/// Rates `grid`, where 0 marks an empty cell. Only puzzles with exactly one
+/// solution can be rated.
+pub fn rate(grid: &[[u8; 9]; 9]) -> Result<Rating, RatingError> {
+    match native::solve_grid(grid, 2).len() {
+        0 => return Err(RatingError::NoSolution),
+        1 => {}
+        _ => return Err(RatingError::NotUnique),
+    }
+
+    let explanation = logic::explain(grid).ok_or(RatingError::NoSolution)?;
+
+    let stalled = match explanation.result {
+        LogicResult::Solved => false,
+        LogicResult::Stalled => true,
+        LogicResult::Contradiction => return Err(RatingError::NoSolution),
+    };
+
+    let hardest = explanation
+        .deductions
+        .iter()
+        .map(|deduction| deduction.technique)
+        .max_by(|a, b| technique_score(*a).total_cmp(&technique_score(*b)));
+
+    let hardest_uses = explanation
+        .deductions
+        .iter()
+        .filter(|deduction| Some(deduction.technique) == hardest)
+        .count();
+
+    let score = if stalled {
+        STALLED_SCORE
+    } else {
+        hardest.map_or(0.0, technique_score)
+    };
+
+    Ok(Rating {
+        difficulty: Difficulty::from_score(score),
+        score,
+        hardest,
+        hardest_uses,
+        steps: explanation.deductions.len(),
+        stalled,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_line;
+
+    fn rate_line(line: &str) -> Result<Rating, RatingError> {
+        rate(&parse_line(line).unwrap().0)
+    }
+
+    #[test]
+    fn only_unique_puzzles_are_rated() {
+        // Unsatisfiable, though no two givens conflict
+        assert_eq!(
+            rate_line(
+                "82.........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4.."
+            )
+            .unwrap_err(),
+            RatingError::NoSolution
+        );
+        assert_eq!(
+            rate_line(
+                "5...7....6...95....9.....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+            )
+            .unwrap_err(),
+            RatingError::NotUnique
+        );
+
+        let rating = rate_line(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        assert_eq!(rating.difficulty, Difficulty::Easy);
+        assert_eq!(rating.to_json()["hardest"], "hidden single");
+    }
+}
@@ -0,0 +1,455 @@
+//! The [`Solver`] backends and what solving a puzzle with them can find.
+
+use std::{
+    error, fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Puzzle,
+    dlx::DlxSolver,
+    encoding::Encoding,
+    grid::{Cell, Conflict, Digit},
+    logic::{Explanation, LogicalSolver},
+    native::NativeSolver,
+};
+
+/// Solver backends the editor can switch between, the first being the default.
+pub const BACKENDS: [&dyn Solver; 6] = [
+    &Z3Solver::new(Encoding::Int),
+    &Z3Solver::new(Encoding::OneHot),
+    &Z3Solver::new(Encoding::BitVec),
+    &NativeSolver,
+    &DlxSolver,
+    &LogicalSolver,
+];
+
+/// Solutions a backend found for a puzzle.
+#[derive(Debug)]
+pub struct Found {
+    pub solutions: Vec<Puzzle>,
+    /// Why the backend gave up before either reaching the limit or proving
+    /// there are no further solutions, if it did.
+    pub unknown: Option<UnknownReason>,
+    /// Steps taken by backends that solve the way a person would.
+    pub explanation: Option<Explanation>,
+}
+
+/// A way of finding the solutions of a `Puzzle`. Backends are shared between
+/// batch worker threads, so they must be `Sync`.
+///
+/// Every backend stops at the timeout or cancellation in `limits`: the native
+/// and DLX backends check them at each step of their search, and the logical
+/// solver passes them on to z3 when it stalls. Only z3 has resource units, so
+/// the others ignore the resource limit.
+pub trait Solver: Sync {
+    /// Short name shown to the user.
+    fn name(&self) -> &'static str;
+
+    /// Finds at most `limit` distinct solutions of `puzzle`, giving up once
+    /// `limits` are exceeded.
+    fn find_solutions(&self, puzzle: &Puzzle, limit: usize, limits: &Limits) -> Found;
+
+    /// Counts the solutions of `puzzle`, stopping once `limit` is reached.
+    /// Returns the reason if the backend gave up before finishing.
+    fn count_solutions(
+        &self,
+        puzzle: &Puzzle,
+        limit: usize,
+        limits: &Limits,
+    ) -> Result<usize, UnknownReason> {
+        let found = self.find_solutions(puzzle, limit, limits);
+        match found.unknown {
+            Some(reason) => Err(reason),
+            None => Ok(found.solutions.len()),
+        }
+    }
+}
+
+/// Solves puzzles by handing the sudoku rules to z3, written in `encoding`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Z3Solver {
+    pub encoding: Encoding,
+}
+
+impl Z3Solver {
+    pub const fn new(encoding: Encoding) -> Self {
+        Self { encoding }
+    }
+}
+
+impl Solver for Z3Solver {
+    fn name(&self) -> &'static str {
+        match self.encoding {
+            Encoding::Int => "z3",
+            Encoding::OneHot => "z3-onehot",
+            Encoding::BitVec => "z3-bitvec",
+        }
+    }
+
+    fn find_solutions(&self, puzzle: &Puzzle, limit: usize, limits: &Limits) -> Found {
+        let mut solutions = puzzle.solutions(limit, self.encoding, limits);
+
+        Found {
+            solutions: solutions.by_ref().collect(),
+            unknown: solutions.reason_unknown(),
+            explanation: None,
+        }
+    }
+
+    fn count_solutions(
+        &self,
+        puzzle: &Puzzle,
+        limit: usize,
+        limits: &Limits,
+    ) -> Result<usize, UnknownReason> {
+        puzzle.count_solutions(limit, self.encoding, limits)
+    }
+}
+
+/// Bounds on how long a solve may run. Without any, a solve runs until it's
+/// done.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) resource_limit: Option<u32>,
+    pub(crate) cancel: Option<CancelToken>,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gives up once the whole solve has taken `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Gives up once a single z3 check has used `resource_limit` of z3's
+    /// resource units. Unlike time, these don't depend on the machine or its
+    /// load, so the same puzzle always gets as far.
+    pub fn resource_limit(mut self, resource_limit: u32) -> Self {
+        self.resource_limit = Some(resource_limit);
+        self
+    }
+
+    /// Gives up once `cancel` is canceled.
+    pub fn cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Whether the solve has been canceled.
+    pub(crate) fn is_canceled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_canceled)
+    }
+
+    /// Why a solve that began at `start` has to stop now, if it has to.
+    pub(crate) fn exceeded(&self, start: Instant) -> Option<UnknownReason> {
+        if self.is_canceled() {
+            return Some(UnknownReason::Canceled);
+        }
+        self.timeout
+            .filter(|&timeout| start.elapsed() >= timeout)
+            .map(|_| UnknownReason::Timeout)
+    }
+}
+
+/// Solutions a backend's own search has found so far, and whether it has to
+/// stop. Shared by every branch of the search.
+pub(crate) struct Search<'a, T> {
+    limit: usize,
+    limits: &'a Limits,
+    start: Instant,
+    pub(crate) solutions: Vec<T>,
+    /// Why the search gave up, once it has.
+    pub(crate) unknown: Option<UnknownReason>,
+}
+
+impl<'a, T> Search<'a, T> {
+    /// Starts a search for at most `limit` solutions within `limits`, for a
+    /// solve that began at `start`.
+    pub(crate) fn new(limit: usize, limits: &'a Limits, start: Instant) -> Self {
+        Self {
+            limit,
+            limits,
+            start,
+            solutions: Vec::new(),
+            unknown: None,
+        }
+    }
+
+    /// Whether the search should stop, having found enough solutions or
+    /// exceeded its limits.
+    pub(crate) fn is_done(&mut self) -> bool {
+        if self.solutions.len() >= self.limit {
+            return true;
+        }
+        if self.unknown.is_none() {
+            self.unknown = self.limits.exceeded(self.start);
+        }
+        self.unknown.is_some()
+    }
+}
+
+/// Lets another thread stop solves that were given it in their [`Limits`].
+/// Clones share the same state, so one can be kept while another is handed
+/// to the solve.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops every solve using this token. Running z3 checks are interrupted
+    /// within a few milliseconds.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Why a solver gave up before finishing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnknownReason {
+    /// The timeout in the [`Limits`] ran out.
+    Timeout,
+    /// A z3 check used up the resource limit in the [`Limits`].
+    ResourceLimit,
+    /// The solve was canceled through its [`CancelToken`].
+    Canceled,
+    /// Any other reason z3 gave, such as running out of memory.
+    Other(String),
+}
+
+impl UnknownReason {
+    /// Reads the reason z3 reports for an `Unknown` result.
+    pub(crate) fn from_z3(reason: &str) -> Self {
+        match reason {
+            "timeout" => UnknownReason::Timeout,
+            "canceled" => UnknownReason::Canceled,
+            _ if reason.contains("resource limit") => UnknownReason::ResourceLimit,
+            _ => UnknownReason::Other(reason.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for UnknownReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnknownReason::Timeout => write!(f, "timeout"),
+            UnknownReason::ResourceLimit => write!(f, "resource limit exceeded"),
+            UnknownReason::Canceled => write!(f, "canceled"),
+            UnknownReason::Other(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+/// How much work `Puzzle::solve` does after finding the first model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveMode {
+    /// Stop at the first solution.
+    First,
+    /// Block the first solution and check whether another one exists.
+    Unique,
+    /// Only count solutions, giving up once the limit is reached.
+    Count(usize),
+}
+
+impl SolveMode {
+    /// Solution cap used by the editor's counting mode.
+    pub const COUNT_LIMIT: usize = 1000;
+
+    pub fn label(&self) -> String {
+        match self {
+            SolveMode::First => "first solution".to_string(),
+            SolveMode::Unique => "check unique".to_string(),
+            SolveMode::Count(limit) => format!("count up to {limit}"),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SolveMode::First => SolveMode::Unique,
+            SolveMode::Unique => SolveMode::Count(Self::COUNT_LIMIT),
+            SolveMode::Count(_) => SolveMode::First,
+        }
+    }
+
+    /// Most solutions worth looking for in this mode.
+    pub fn solution_limit(self) -> usize {
+        match self {
+            SolveMode::First => 1,
+            SolveMode::Unique => 2,
+            SolveMode::Count(limit) => limit,
+        }
+    }
+}
+
+/// What solving a puzzle found.
+#[derive(Debug)]
+pub enum SolveOutcome {
+    /// A solution, which in `SolveMode::Unique` is the only one.
+    Solved {
+        solution: Puzzle,
+        /// Steps taken by backends that solve the way a person would.
+        explanation: Option<Explanation>,
+    },
+    /// Two different solutions, found in `SolveMode::Unique`.
+    NotUnique(Puzzle, Puzzle),
+    /// Number of solutions found in `SolveMode::Count`, at most its limit.
+    Counted(usize),
+    /// The puzzle has no solution.
+    Unsatisfiable {
+        /// A minimal set of givens that contradict each other, as found by
+        /// [`Puzzle::contradiction`].
+        contradiction: Option<Vec<(Cell, Digit)>>,
+    },
+    /// The solver gave up, for the given reason.
+    Unknown(UnknownReason),
+}
+
+impl SolveOutcome {
+    /// Status reported for this outcome by batches and exit codes.
+    pub fn status(&self) -> Status {
+        match self {
+            SolveOutcome::Solved { .. } | SolveOutcome::Counted(1) => Status::Solved,
+            SolveOutcome::NotUnique(..) | SolveOutcome::Counted(2..) => Status::Multiple,
+            SolveOutcome::Unsatisfiable { .. } | SolveOutcome::Counted(0) => Status::Unsat,
+            SolveOutcome::Unknown(_) => Status::Unknown,
+        }
+    }
+}
+
+/// What solving a single puzzle showed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    /// Exactly one solution.
+    Solved,
+    /// No solutions.
+    Unsat,
+    /// The solver gave up.
+    Unknown,
+    /// More than one solution.
+    Multiple,
+}
+
+impl Status {
+    pub const ALL: [Status; 4] = [
+        Status::Solved,
+        Status::Unsat,
+        Status::Unknown,
+        Status::Multiple,
+    ];
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Pad here so the report columns line up
+        f.pad(match self {
+            Status::Solved => "solved",
+            Status::Unsat => "unsat",
+            Status::Unknown => "unknown",
+            Status::Multiple => "multiple",
+        })
+    }
+}
+
+/// Why a puzzle couldn't be solved at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    /// Givens that see each other hold the same digit.
+    ConflictingGivens(Vec<Conflict>),
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::ConflictingGivens(conflicts) => {
+                write!(f, "The givens conflict: ")?;
+                for (i, conflict) in conflicts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{conflict}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for SolveError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grid::Grid, parse::parse_line};
+
+    #[test]
+    fn every_backend_counts_like_z3() {
+        // A well-known puzzle with three givens removed, leaving 10 solutions
+        let (grid, _) = parse_line(
+            "5...7....6...95....9.....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let puzzle = Puzzle::from_array(&grid);
+
+        for limit in [4, 50] {
+            let expected = Z3Solver::default().count_solutions(&puzzle, limit, &Limits::new());
+            assert_eq!(expected, Ok(limit.min(10)));
+
+            for solver in BACKENDS {
+                assert_eq!(
+                    solver.count_solutions(&puzzle, limit, &Limits::new()),
+                    expected,
+                    "{} with limit {limit}",
+                    solver.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_backend_stops_when_canceled() {
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let limits = Limits::new().cancel(cancel);
+
+        let puzzle = Puzzle::new(Grid::new());
+        for solver in BACKENDS {
+            assert_eq!(
+                solver.count_solutions(&puzzle, 200_000, &limits),
+                Err(UnknownReason::Canceled),
+                "{}",
+                solver.name()
+            );
+        }
+    }
+
+    #[test]
+    fn every_backend_stops_at_the_timeout() {
+        let limits = Limits::new().timeout(Duration::from_millis(50));
+
+        let puzzle = Puzzle::new(Grid::new());
+        for solver in BACKENDS {
+            assert_eq!(
+                solver.count_solutions(&puzzle, usize::MAX, &limits),
+                Err(UnknownReason::Timeout),
+                "{}",
+                solver.name()
+            );
+        }
+    }
+}